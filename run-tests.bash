@@ -13,9 +13,13 @@ fi
 cargo test
 cargo test --all-features
 cargo test --no-default-features
+cargo test --no-default-features --features brotli
 cargo test --no-default-features --features charsets
 cargo test --no-default-features --features compress
+cargo test --no-default-features --features cookies
+cargo test --no-default-features --features digest
 cargo test --no-default-features --features form
+cargo test --no-default-features --features mime
 cargo test --no-default-features --features multipart-form
 cargo test --no-default-features --features json
 cargo test --no-default-features --features tls
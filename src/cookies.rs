@@ -0,0 +1,330 @@
+use std::cmp::Reverse;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use http::header::{HeaderMap, SET_COOKIE};
+use url::Url;
+
+use crate::error::Result;
+
+#[derive(Clone, Debug)]
+struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    expires: Option<SystemTime>,
+}
+
+impl Cookie {
+    /// Parse a `Set-Cookie` header value received from the given URL.
+    ///
+    /// Returns `None` if the cookie is invalid or if it is not allowed to be set by this URL.
+    fn parse(header: &str, url: &Url, now: SystemTime) -> Option<Cookie> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut parts = header.split(';');
+
+        let (name, value) = split_pair(parts.next()?)?;
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url).to_owned(),
+            secure: false,
+            expires: None,
+        };
+
+        let mut max_age = None;
+        let mut expires = None;
+
+        for attr in parts {
+            let (key, val) = split_pair(attr).unwrap_or((attr.trim(), ""));
+            if key.eq_ignore_ascii_case("domain") {
+                let domain = val.trim_start_matches('.').to_ascii_lowercase();
+                if domain.is_empty() {
+                    continue;
+                }
+                // Refuse top level domains, a cookie set for `com` would be sent to every `.com` site.
+                if !domain_match(&host, &domain) || (domain != host && !domain.contains('.')) {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            } else if key.eq_ignore_ascii_case("path") {
+                if val.starts_with('/') {
+                    cookie.path = val.to_owned();
+                }
+            } else if key.eq_ignore_ascii_case("secure") {
+                cookie.secure = true;
+            } else if key.eq_ignore_ascii_case("max-age") {
+                if let Ok(secs) = val.parse::<i64>() {
+                    // A Max-Age too large to be represented never expires.
+                    max_age = Some(if secs <= 0 {
+                        Some(SystemTime::UNIX_EPOCH)
+                    } else {
+                        now.checked_add(Duration::from_secs(secs as u64))
+                    });
+                }
+            } else if key.eq_ignore_ascii_case("expires") {
+                if let Ok(date) = httpdate::parse_http_date(val) {
+                    expires = Some(date);
+                }
+            }
+        }
+
+        // Max-Age has precedence over Expires.
+        cookie.expires = max_age.unwrap_or(expires);
+        Some(cookie)
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        matches!(self.expires, Some(expires) if expires <= now)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(&host, &self.domain)
+        };
+
+        domain_ok && path_match(url.path(), &self.path) && (!self.secure || url.scheme() == "https")
+    }
+
+    fn is_same(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+fn split_pair(s: &str) -> Option<(&str, &str)> {
+    let idx = s.find('=')?;
+    Some((s[..idx].trim(), s[idx + 1..].trim()))
+}
+
+/// The directory of the URL's path, as described in RFC 6265, section 5.1.4.
+fn default_path(url: &Url) -> &str {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(idx) => &path[..idx],
+    }
+}
+
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<IpAddr>().is_err())
+}
+
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path) && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// `CookieJar` stores the cookies set by servers and sends them back on the following requests.
+///
+/// Cloning a `CookieJar` is cheap, every clone shares the same cookies. This makes it possible to keep
+/// a handle to the jar given to a `Session` to inspect its content.
+///
+/// This type only exists when the `cookies` feature is enabled.
+#[derive(Clone, Debug, Default)]
+pub struct CookieJar {
+    cookies: Arc<Mutex<Vec<Cookie>>>,
+}
+
+impl CookieJar {
+    /// Create a new, empty `CookieJar`.
+    pub fn new() -> CookieJar {
+        CookieJar::default()
+    }
+
+    /// Get the names and values of the cookies that would be sent to the given URL.
+    pub fn cookies<U>(&self, url: U) -> Result<Vec<(String, String)>>
+    where
+        U: AsRef<str>,
+    {
+        let url = Url::parse(url.as_ref())?;
+        Ok(self
+            .matching(&url)
+            .into_iter()
+            .map(|cookie| (cookie.name, cookie.value))
+            .collect())
+    }
+
+    /// Remove every cookie from this `CookieJar`.
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+
+    /// Store the cookies set by the headers of a response received from the given URL.
+    pub(crate) fn store_response_cookies(&self, url: &Url, headers: &HeaderMap) {
+        let now = SystemTime::now();
+        let mut cookies = self.cookies.lock().unwrap();
+
+        for header in headers.get_all(SET_COOKIE) {
+            let cookie = match header.to_str().ok().and_then(|val| Cookie::parse(val, url, now)) {
+                Some(cookie) => cookie,
+                None => {
+                    debug!("ignoring invalid cookie {:?}", header);
+                    continue;
+                }
+            };
+
+            cookies.retain(|other| !other.is_same(&cookie));
+            if !cookie.is_expired(now) {
+                cookies.push(cookie);
+            }
+        }
+    }
+
+    /// Get the value of the `Cookie` header to send to the given URL, if any.
+    pub(crate) fn header_for_url(&self, url: &Url) -> Option<String> {
+        let cookies = self.matching(url);
+        if cookies.is_empty() {
+            return None;
+        }
+
+        let pairs: Vec<String> = cookies
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    fn matching(&self, url: &Url) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|cookie| !cookie.is_expired(now));
+
+        let mut matching: Vec<Cookie> = cookies.iter().filter(|cookie| cookie.matches(url)).cloned().collect();
+        // Cookies with longer paths are listed first.
+        matching.sort_by_key(|cookie| Reverse(cookie.path.len()));
+        matching
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::{HeaderMap, HeaderValue, SET_COOKIE};
+    use url::Url;
+
+    use super::CookieJar;
+
+    fn store(jar: &CookieJar, url: &str, cookies: &[&'static str]) {
+        let mut headers = HeaderMap::new();
+        for &cookie in cookies {
+            headers.append(SET_COOKIE, HeaderValue::from_static(cookie));
+        }
+        jar.store_response_cookies(&Url::parse(url).unwrap(), &headers);
+    }
+
+    fn header(jar: &CookieJar, url: &str) -> Option<String> {
+        jar.header_for_url(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_cookie_jar_multiple_cookies() {
+        let jar = CookieJar::new();
+        store(&jar, "http://example.com/", &["a=1", "b=2; HttpOnly"]);
+        assert_eq!(header(&jar, "http://example.com/"), Some("a=1; b=2".to_owned()));
+        assert_eq!(header(&jar, "http://example.org/"), None);
+    }
+
+    #[test]
+    fn test_cookie_jar_replace() {
+        let jar = CookieJar::new();
+        store(&jar, "http://example.com/", &["a=1"]);
+        store(&jar, "http://example.com/", &["a=2"]);
+        assert_eq!(header(&jar, "http://example.com/"), Some("a=2".to_owned()));
+    }
+
+    #[test]
+    fn test_cookie_jar_domain() {
+        let jar = CookieJar::new();
+        store(&jar, "http://www.example.com/", &["a=1; Domain=.example.com", "b=2"]);
+        assert_eq!(header(&jar, "http://example.com/"), Some("a=1".to_owned()));
+        assert_eq!(header(&jar, "http://api.example.com/"), Some("a=1".to_owned()));
+        assert_eq!(header(&jar, "http://www.example.com/"), Some("a=1; b=2".to_owned()));
+        assert_eq!(header(&jar, "http://badexample.com/"), None);
+    }
+
+    #[test]
+    fn test_cookie_jar_foreign_domain() {
+        let jar = CookieJar::new();
+        store(
+            &jar,
+            "http://example.com/",
+            &["a=1; Domain=example.org", "b=2; Domain=com"],
+        );
+        assert_eq!(header(&jar, "http://example.org/"), None);
+        assert_eq!(header(&jar, "http://example.com/"), None);
+    }
+
+    #[test]
+    fn test_cookie_jar_path() {
+        let jar = CookieJar::new();
+        store(&jar, "http://example.com/docs/index.html", &["a=1", "b=2; Path=/"]);
+        assert_eq!(header(&jar, "http://example.com/docs"), Some("a=1; b=2".to_owned()));
+        assert_eq!(
+            header(&jar, "http://example.com/docs/web/"),
+            Some("a=1; b=2".to_owned())
+        );
+        assert_eq!(header(&jar, "http://example.com/docsets"), Some("b=2".to_owned()));
+        assert_eq!(header(&jar, "http://example.com/"), Some("b=2".to_owned()));
+    }
+
+    #[test]
+    fn test_cookie_jar_secure() {
+        let jar = CookieJar::new();
+        store(&jar, "https://example.com/", &["a=1; Secure"]);
+        assert_eq!(header(&jar, "https://example.com/"), Some("a=1".to_owned()));
+        assert_eq!(header(&jar, "http://example.com/"), None);
+    }
+
+    #[test]
+    fn test_cookie_jar_expiration() {
+        let jar = CookieJar::new();
+        store(
+            &jar,
+            "http://example.com/",
+            &[
+                "a=1; Max-Age=3600",
+                "b=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+                "c=3; Max-Age=3600; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+                "d=4; Max-Age=0",
+                "e=5; Max-Age=9223372036854775807; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            ],
+        );
+        assert_eq!(header(&jar, "http://example.com/"), Some("a=1; c=3; e=5".to_owned()));
+
+        store(&jar, "http://example.com/", &["a=1; Max-Age=-1"]);
+        assert_eq!(header(&jar, "http://example.com/"), Some("c=3; e=5".to_owned()));
+    }
+
+    #[test]
+    fn test_cookie_jar_cookies() {
+        let jar = CookieJar::new();
+        store(&jar, "http://example.com/", &["a=1"]);
+        assert_eq!(
+            jar.cookies("http://example.com/foo").unwrap(),
+            vec![("a".to_owned(), "1".to_owned())]
+        );
+
+        jar.clear();
+        assert!(jar.cookies("http://example.com/").unwrap().is_empty());
+    }
+}
@@ -17,12 +17,16 @@ pub enum InvalidResponseKind {
     StatusCode,
     /// Error parsing header
     Header,
+    /// Header continued on the next line, the obsolete line folding
+    FoldedHeader,
     /// Error decoding chunk size
     ChunkSize,
     /// Error decoding chunk
     Chunk,
     /// Invalid Content-Length header
     ContentLength,
+    /// Both Content-Length and chunked Transfer-Encoding headers, which make the length of the body ambiguous
+    AmbiguousLength,
 }
 
 impl Display for InvalidResponseKind {
@@ -35,13 +39,31 @@ impl Display for InvalidResponseKind {
             StatusLine => write!(f, "invalid status line"),
             StatusCode => write!(f, "invalid status code"),
             Header => write!(f, "invalid header"),
+            FoldedHeader => write!(f, "folded header line (obs-fold)"),
             ChunkSize => write!(f, "invalid chunk size"),
             Chunk => write!(f, "invalid chunk"),
             ContentLength => write!(f, "invalid content length"),
+            AmbiguousLength => write!(f, "both content length and chunked transfer encoding"),
         }
     }
 }
 
+impl StdError for InvalidResponseKind {}
+
+impl InvalidResponseKind {
+    /// Create an error which gives the offending line, and its offset from the start of the response head.
+    pub(crate) fn at_line(self, line: &[u8], offset: Option<u64>) -> Error {
+        const MAX_LINE_LEN: usize = 256;
+
+        ErrorKind::InvalidResponse {
+            kind: self,
+            line: Some(line[..line.len().min(MAX_LINE_LEN)].to_vec()),
+            offset,
+        }
+        .into()
+    }
+}
+
 /// Common errors that can occur during HTTP requests.
 #[derive(Debug)]
 pub enum ErrorKind {
@@ -58,16 +80,29 @@ pub enum ErrorKind {
     Http(http::Error),
     /// IO Error
     Io(io::Error),
-    /// Invalid base URL given to the Request.
+    /// The URL given to the Request uses an unsupported scheme.
     InvalidBaseUrl,
+    /// The URL given to the Request could not be parsed.
+    InvalidUrl(url::ParseError),
     /// An URL with an invalid host was found while processing the request.
     InvalidUrlHost,
     /// The URL scheme is unknown and the port is missing.
     InvalidUrlPort,
     /// Server sent an invalid response.
-    InvalidResponse(InvalidResponseKind),
+    InvalidResponse {
+        /// The part of the response which is invalid.
+        kind: InvalidResponseKind,
+        /// The offending line or header value, truncated to 256 bytes, if it is known.
+        line: Option<Vec<u8>>,
+        /// The offset of the offending line from the start of the response head, if it is known.
+        offset: Option<u64>,
+    },
     /// Too many redirections
     TooManyRedirections,
+    /// The redirect policy refused to follow a redirection to this URL.
+    RedirectRefused(url::Url),
+    /// A redirection from `https` to this `http` URL was refused, see `RequestBuilder::allow_downgrade`.
+    InsecureRedirect(url::Url),
     /// Status code indicates failure
     StatusCode(http::StatusCode),
     /// JSON decoding/encoding error.
@@ -86,6 +121,33 @@ pub enum ErrorKind {
     InvalidMimeType(String),
     /// TLS was not enabled by features.
     TlsDisabled,
+    /// The client certificate or its private key could not be parsed.
+    InvalidIdentity,
+    /// The HTTP version is not supported, or cannot be used to send this request.
+    UnsupportedVersion(http::Version),
+    /// The form of the request target cannot be used with the method of this request.
+    InvalidRequestTarget(crate::RequestTarget),
+    /// The head of the response is larger than the maximum header size.
+    HeadersTooLarge,
+    /// The body of the response is larger than the maximum body size.
+    BodyTooLarge,
+    /// The connection was closed before the whole body of the response was received.
+    IncompleteBody,
+    /// The connection was closed after a part of the head of the response was received, before its end.
+    IncompleteResponse,
+    /// The request was cancelled with its cancel token.
+    Cancelled,
+    /// A timeout was reached: the connect, read or write timeout, or the timeout of the whole request.
+    Timeout,
+    /// The value given for this header contains bytes which are not allowed in header values, like control
+    /// characters or line breaks.
+    InvalidHeaderValue(http::header::HeaderName),
+    /// The requests of a `Pipeline` must all be sent to the same server.
+    PipelineOrigin,
+    /// The connection of a `Pipeline` was closed, or is still used by the body of a previous response.
+    PipelineBroken,
+    /// Trailers were given for a request whose body is not sent with the chunked transfer encoding.
+    TrailersWithoutChunkedBody,
     /// WebPKI error.
     #[cfg(feature = "tls-rustls")]
     WebPKI(webpki::Error),
@@ -117,10 +179,26 @@ impl Display for Error {
             Http(ref e) => write!(w, "Http Error: {}", e),
             Io(ref e) => write!(w, "Io Error: {}", e),
             InvalidBaseUrl => write!(w, "Invalid base URL"),
+            InvalidUrl(ref e) => write!(w, "Invalid URL: {}", e),
             InvalidUrlHost => write!(w, "URL is missing a host"),
             InvalidUrlPort => write!(w, "URL is missing a port"),
-            InvalidResponse(ref k) => write!(w, "InvalidResponse: {}", k),
+            InvalidResponse {
+                ref kind,
+                ref line,
+                offset,
+            } => {
+                write!(w, "Invalid response: {}", kind)?;
+                if let Some(offset) = offset {
+                    write!(w, " at byte {}", offset)?;
+                }
+                if let Some(line) = line {
+                    write!(w, ": {:?}", String::from_utf8_lossy(line))?;
+                }
+                Ok(())
+            }
             TooManyRedirections => write!(w, "Too many redirections"),
+            RedirectRefused(ref url) => write!(w, "Redirection to {} refused by the redirect policy", url),
+            InsecureRedirect(ref url) => write!(w, "Redirection from https to {} refused", url),
             StatusCode(ref sc) => write!(w, "Status code {} indicates failure", sc),
             #[cfg(feature = "json")]
             Json(ref e) => write!(w, "Json Error: {}", e),
@@ -132,6 +210,19 @@ impl Display for Error {
             InvalidDNSName(ref e) => write!(w, "Invalid DNS name: {}", e),
             InvalidMimeType(ref e) => write!(w, "Invalid mime type: {}", e),
             TlsDisabled => write!(w, "TLS is disabled, activate tls or tls-rustls feature"),
+            InvalidIdentity => write!(w, "Invalid client certificate or private key"),
+            UnsupportedVersion(version) => write!(w, "Unsupported HTTP version {:?}", version),
+            InvalidRequestTarget(target) => write!(w, "Request target {:?} cannot be used with this method", target),
+            HeadersTooLarge => write!(w, "Response headers are larger than the maximum header size"),
+            BodyTooLarge => write!(w, "Response body is larger than the maximum body size"),
+            IncompleteBody => write!(w, "Connection closed before the end of the response body"),
+            IncompleteResponse => write!(w, "Connection closed before the end of the response head"),
+            Cancelled => write!(w, "Request cancelled"),
+            Timeout => write!(w, "Request timed out"),
+            InvalidHeaderValue(ref name) => write!(w, "Invalid value for header {}", name),
+            PipelineOrigin => write!(w, "Pipelined requests must be sent to the same server"),
+            PipelineBroken => write!(w, "Pipeline connection closed or still used by a previous response"),
+            TrailersWithoutChunkedBody => write!(w, "Trailers can only be sent with a chunked body"),
             #[cfg(feature = "tls-rustls")]
             WebPKI(ref e) => write!(w, "WebPKI error: {}", e),
         }
@@ -139,18 +230,24 @@ impl Display for Error {
 }
 
 impl StdError for Error {
-    fn cause(&self) -> Option<&dyn StdError> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         use ErrorKind::*;
 
         match *self.0 {
             Io(ref e) => Some(e),
             Http(ref e) => Some(e),
+            InvalidUrl(ref e) => Some(e),
+            InvalidResponse { ref kind, .. } => Some(kind),
             #[cfg(feature = "json")]
             Json(ref e) => Some(e),
+            #[cfg(feature = "form")]
+            UrlEncoded(ref e) => Some(e),
             #[cfg(feature = "tls")]
             Tls(ref e) => Some(e),
             #[cfg(feature = "tls-rustls")]
             InvalidDNSName(ref e) => Some(e),
+            #[cfg(feature = "tls-rustls")]
+            WebPKI(ref e) => Some(e),
             _ => None,
         }
     }
@@ -164,6 +261,15 @@ impl From<Infallible> for Error {
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
+        // Errors raised by the readers of this crate are wrapped in an `io::Error`, give them back.
+        if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            let inner = err.into_inner().and_then(|inner| inner.downcast::<Error>().ok());
+            return *inner.expect("the inner error is an Error");
+        }
+        // Sockets with a timeout fail with `WouldBlock` on some platforms and `TimedOut` on others.
+        if matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) {
+            return Error(Box::new(ErrorKind::Timeout));
+        }
         Error(Box::new(ErrorKind::Io(err)))
     }
 }
@@ -180,6 +286,12 @@ impl From<http::header::InvalidHeaderValue> for Error {
     }
 }
 
+impl From<url::ParseError> for Error {
+    fn from(err: url::ParseError) -> Error {
+        Error(Box::new(ErrorKind::InvalidUrl(err)))
+    }
+}
+
 #[cfg(feature = "tls")]
 impl From<native_tls::Error> for Error {
     fn from(err: native_tls::Error) -> Error {
@@ -216,19 +328,24 @@ impl From<ErrorKind> for Error {
 
 impl From<InvalidResponseKind> for Error {
     fn from(kind: InvalidResponseKind) -> Error {
-        ErrorKind::InvalidResponse(kind).into()
+        ErrorKind::InvalidResponse {
+            kind,
+            line: None,
+            offset: None,
+        }
+        .into()
     }
 }
 
 impl From<Error> for io::Error {
     fn from(err: Error) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, err)
+        io::Error::other(err)
     }
 }
 
 impl From<InvalidResponseKind> for io::Error {
     fn from(kind: InvalidResponseKind) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, Error(Box::new(ErrorKind::InvalidResponse(kind))))
+        io::Error::other(Error::from(kind))
     }
 }
 
@@ -241,3 +358,32 @@ impl From<webpki::Error> for Error {
 
 /// Wrapper for the `Result` type with an `Error`.
 pub type Result<T = ()> = result::Result<T, Error>;
+
+#[test]
+fn test_error_source() {
+    let err = Error::from(io::Error::other("boom"));
+    let source = err.source().expect("missing source");
+    assert_eq!(source.to_string(), "boom");
+    assert!(source.downcast_ref::<io::Error>().is_some());
+
+    let err = Error::from(url::ParseError::EmptyHost);
+    assert!(err.source().and_then(|e| e.downcast_ref::<url::ParseError>()).is_some());
+
+    let err = Error::from(InvalidResponseKind::ChunkSize);
+    assert_eq!(err.to_string(), "Invalid response: invalid chunk size");
+    assert!(err.source().is_some());
+
+    assert!(Error::from(ErrorKind::TooManyRedirections).source().is_none());
+}
+
+#[test]
+fn test_error_timeout() {
+    for kind in [io::ErrorKind::TimedOut, io::ErrorKind::WouldBlock] {
+        let err = Error::from(io::Error::from(kind));
+        assert!(matches!(err.kind(), ErrorKind::Timeout));
+        assert_eq!(err.to_string(), "Request timed out");
+    }
+
+    let err = Error::from(io::Error::from(io::ErrorKind::ConnectionReset));
+    assert!(matches!(err.kind(), ErrorKind::Io(_)));
+}
@@ -1,10 +1,11 @@
 use std::io;
 use std::iter::{self, FusedIterator};
-use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
 use std::sync::mpsc::channel;
 use std::thread;
 use std::time::{Duration, Instant};
 
+use socket2::{Domain, Protocol, Socket, Type};
 use url::Host;
 
 const RACE_DELAY: Duration = Duration::from_millis(200);
@@ -12,16 +13,27 @@ const RACE_DELAY: Duration = Duration::from_millis(200);
 /// This function implements a basic form of the happy eyeballs RFC to quickly connect
 /// to a domain which is available in both IPv4 and IPv6. Connection attempts are raced
 /// against each other and the first to connect successfully wins the race.
-pub fn connect(host: &Host<&str>, port: u16, timeout: Duration, deadline: Option<Instant>) -> io::Result<TcpStream> {
+///
+/// If a local address is given, the sockets are bound to it and only the addresses of the same family are tried.
+pub fn connect(
+    host: &Host<&str>,
+    port: u16,
+    local_addr: Option<IpAddr>,
+    timeout: Duration,
+    deadline: Option<Instant>,
+) -> io::Result<TcpStream> {
     let addrs: Vec<_> = match *host {
-        Host::Domain(domain) => (domain, port).to_socket_addrs()?.collect(),
-        Host::Ipv4(ip) => return TcpStream::connect_timeout(&(IpAddr::V4(ip), port).into(), timeout),
-        Host::Ipv6(ip) => return TcpStream::connect_timeout(&(IpAddr::V6(ip), port).into(), timeout),
+        Host::Domain(domain) => (domain, port)
+            .to_socket_addrs()?
+            .filter(|addr| local_addr.is_none_or(|local| local.is_ipv4() == addr.is_ipv4()))
+            .collect(),
+        Host::Ipv4(ip) => return connect_timeout(&(IpAddr::V4(ip), port).into(), local_addr, timeout, deadline),
+        Host::Ipv6(ip) => return connect_timeout(&(IpAddr::V6(ip), port).into(), local_addr, timeout, deadline),
     };
 
     if let [addr] = &addrs[..] {
         debug!("DNS returned only one address, using fast path");
-        return TcpStream::connect_timeout(addr, timeout);
+        return connect_timeout(addr, local_addr, timeout, deadline);
     }
 
     let ipv4 = addrs.iter().filter(|a| a.is_ipv4());
@@ -62,13 +74,7 @@ pub fn connect(host: &Host<&str>, port: u16, timeout: Duration, deadline: Option
         thread::spawn(move || {
             debug!("trying to connect to {}", addr);
 
-            let res = match deadline.map(|deadline| deadline.checked_duration_since(Instant::now())) {
-                None => TcpStream::connect_timeout(&addr, timeout),
-                Some(Some(timeout1)) => TcpStream::connect_timeout(&addr, timeout.min(timeout1)),
-                Some(None) => Err(io::ErrorKind::TimedOut.into()),
-            };
-
-            let _ = tx.send((addr, res));
+            let _ = tx.send((addr, connect_timeout(&addr, local_addr, timeout, deadline)));
         });
 
         if let Ok((addr, res)) = rx.recv_timeout(RACE_DELAY) {
@@ -96,7 +102,37 @@ pub fn connect(host: &Host<&str>, port: u16, timeout: Duration, deadline: Option
         start.elapsed().as_millis()
     );
 
-    Err(first_err.unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "no DNS entries found")))
+    Err(first_err.unwrap_or_else(|| io::Error::other("no DNS entries found")))
+}
+
+/// Connect to the address, giving up after the timeout or when the deadline is reached, whichever comes first.
+fn connect_timeout(
+    addr: &SocketAddr,
+    local_addr: Option<IpAddr>,
+    timeout: Duration,
+    deadline: Option<Instant>,
+) -> io::Result<TcpStream> {
+    let timeout = match deadline.map(|deadline| deadline.checked_duration_since(Instant::now())) {
+        None => timeout,
+        Some(Some(remaining)) if remaining > Duration::from_secs(0) => timeout.min(remaining),
+        Some(_) => return Err(io::ErrorKind::TimedOut.into()),
+    };
+
+    let local_addr = match local_addr {
+        Some(local_addr) => local_addr,
+        None => return TcpStream::connect_timeout(addr, timeout),
+    };
+
+    let domain = if addr.is_ipv4() { Domain::ipv4() } else { Domain::ipv6() };
+    let socket = Socket::new(domain, Type::stream(), Some(Protocol::tcp()))?;
+    socket.bind(&SocketAddr::new(local_addr, 0).into()).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("cannot bind to local address {}: {}", local_addr, err),
+        )
+    })?;
+    socket.connect_timeout(&(*addr).into(), timeout)?;
+    Ok(socket.into_tcp_stream())
 }
 
 fn intertwine<T, A, B>(mut ita: A, mut itb: B) -> impl Iterator<Item = T>
@@ -140,3 +176,31 @@ fn test_intertwine_right() {
     let x: Vec<u32> = intertwine(vec![1, 2, 3].into_iter(), vec![4, 5, 6, 100, 101].into_iter()).collect();
     assert_eq!(&x[..], &[1, 4, 2, 5, 3, 6, 100, 101][..]);
 }
+
+#[test]
+fn test_connect_deadline_reached() {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 1));
+    let err = connect_timeout(&addr, None, Duration::from_secs(1), Some(Instant::now())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn test_connect_local_addr() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let local_addr = IpAddr::from([127, 0, 0, 1]);
+
+    let stream = connect_timeout(&addr, Some(local_addr), Duration::from_secs(1), None).unwrap();
+    assert_eq!(stream.local_addr().unwrap().ip(), local_addr);
+}
+
+#[test]
+fn test_connect_local_addr_unavailable() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Documentation addresses are never assigned to a local interface.
+    let local_addr = IpAddr::from([192, 0, 2, 1]);
+    let err = connect_timeout(&addr, Some(local_addr), Duration::from_secs(1), None).unwrap_err();
+    assert!(err.to_string().contains("192.0.2.1"));
+}
@@ -38,10 +38,14 @@
 //! ```
 //!
 //! # Features
+//! * `brotli` support for decompressing response bodies compressed with Brotli, implies `compress`
 //! * `charsets` support for decoding more text encodings than just UTF-8
 //! * `compress` support for decompressing response bodies (**default**)
+//! * `cookies` support for storing cookies in a `CookieJar`
+//! * `digest` support for HTTP digest authentication
 //! * `json` support for serialization and deserialization
 //! * `form` support for url encoded forms (does not include support for multipart)
+//! * `mime` support for parsing the `Content-Type` of a response into a `mime::Mime`, implied by `multipart-form`
 //! * `tls` support for tls connections (**default**)
 //! * `tls-rustls` support for TLS connections using `rustls` instead of `native-tls`
 //! * `multipart-form` support for multipart forms (does not include support for url encoding)
@@ -63,25 +67,37 @@ macro_rules! warn {
 
 #[cfg(feature = "charsets")]
 pub mod charsets;
+#[cfg(feature = "cookies")]
+mod cookies;
 mod error;
 mod happy;
 #[cfg(feature = "multipart")]
 mod multipart;
 mod parsing;
+mod pool;
 mod request;
 mod streams;
+mod throttle;
 mod tls;
 
+#[cfg(feature = "cookies")]
+pub use crate::cookies::CookieJar;
 pub use crate::error::{Error, ErrorKind, InvalidResponseKind, Result};
 #[cfg(feature = "multipart")]
 pub use crate::multipart::{Multipart, MultipartBuilder, MultipartFile};
 pub use crate::parsing::{Response, ResponseReader};
 pub use crate::request::proxy::{ProxySettings, ProxySettingsBuilder};
-pub use crate::request::{body, PreparedRequest, RequestBuilder, RequestInspector, Session};
+pub use crate::request::{
+    body, Pipeline, PreparedRequest, RedirectAction, RedirectMethodPolicy, RequestBuilder, RequestInspector,
+    RequestTarget, RetryPolicy, Session,
+};
+pub use crate::streams::ConnectionInfo;
+pub use crate::tls::{TlsInfo, TlsVersion};
 #[cfg(feature = "charsets")]
 pub use crate::{charsets::Charset, parsing::TextReader};
 pub use http::Method;
 pub use http::StatusCode;
+pub use http::Version;
 
 pub mod header {
     //! This module is a re-export of the `http` crate's `header` module.
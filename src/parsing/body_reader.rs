@@ -1,46 +1,162 @@
 use std::io::{self, BufRead, BufReader, Read, Take};
+use std::mem;
 
 use http::header::{HeaderMap, HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING};
 
-use crate::error::{InvalidResponseKind, Result};
+use crate::error::{Error, ErrorKind, InvalidResponseKind, Result};
 use crate::parsing::chunked_reader::ChunkedReader;
+use crate::pool::PoolHandle;
 use crate::streams::BaseStream;
+use crate::throttle::Throttle;
 
 #[derive(Debug)]
-pub enum BodyReader {
+enum Inner {
     Chunked(ChunkedReader<BaseStream>),
     Length(Take<BufReader<BaseStream>>),
     Close(BufReader<BaseStream>),
+    Released,
+}
+
+impl Inner {
+    fn is_done(&self) -> bool {
+        match self {
+            Inner::Chunked(r) => r.is_done(),
+            Inner::Length(r) => r.limit() == 0,
+            Inner::Close(_) | Inner::Released => false,
+        }
+    }
+}
+
+/// The connection was closed before `Content-Length` bytes were received.
+fn incomplete_body() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, Error::from(ErrorKind::IncompleteBody))
+}
+
+#[derive(Debug)]
+pub struct BodyReader {
+    inner: Inner,
+    pool: Option<PoolHandle>,
+    throttle: Option<Throttle>,
+    trailers: HeaderMap,
+}
+
+impl BodyReader {
+    /// Give the connection back to the pool once the whole body has been read.
+    fn release_if_done(&mut self) {
+        if self.pool.is_none() || !self.inner.is_done() {
+            return;
+        }
+
+        let reader = match mem::replace(&mut self.inner, Inner::Released) {
+            Inner::Chunked(mut r) => {
+                self.trailers = r.take_trailers();
+                r.into_inner()
+            }
+            Inner::Length(r) => r.into_inner(),
+            _ => unreachable!(),
+        };
+        if let Some(pool) = self.pool.take() {
+            pool.release(reader);
+        }
+    }
+
+    /// Get the number of bytes left in the body, if it is known.
+    pub fn remaining_len(&self) -> Option<u64> {
+        match &self.inner {
+            Inner::Length(r) => Some(r.limit()),
+            Inner::Released => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Get the trailer fields sent after a chunked body, which are empty until the whole body has been read.
+    pub fn trailers(&self) -> &HeaderMap {
+        match &self.inner {
+            Inner::Chunked(r) => r.trailers(),
+            _ => &self.trailers,
+        }
+    }
+
+    /// A connection in an unknown state must never be reused.
+    fn discard(&mut self, err: io::Error) -> io::Error {
+        self.pool = None;
+        err
+    }
 }
 
 impl Read for BodyReader {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        match self {
-            BodyReader::Chunked(r) => r.read(buf),
-            BodyReader::Length(r) => r.read(buf),
-            BodyReader::Close(r) => r.read(buf),
+        let buf = match &self.throttle {
+            Some(throttle) => {
+                let len = throttle.limit(buf.len());
+                &mut buf[..len]
+            }
+            None => buf,
+        };
+        let n = match &mut self.inner {
+            Inner::Chunked(r) => r.read(buf),
+            Inner::Length(r) => match r.read(buf) {
+                Ok(0) if !buf.is_empty() && r.limit() > 0 => Err(incomplete_body()),
+                res => res,
+            },
+            Inner::Close(r) => r.read(buf),
+            Inner::Released => Ok(0),
+        }
+        .map_err(|e| self.discard(e))?;
+        if let Some(throttle) = &mut self.throttle {
+            throttle.consume(n);
         }
+        self.release_if_done();
+        Ok(n)
     }
 }
 
 impl BufRead for BodyReader {
     #[inline]
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        match self {
-            BodyReader::Chunked(r) => r.fill_buf(),
-            BodyReader::Length(r) => r.fill_buf(),
-            BodyReader::Close(r) => r.fill_buf(),
+        let is_empty = match &mut self.inner {
+            Inner::Chunked(r) => r.fill_buf().map(|buf| buf.is_empty()),
+            Inner::Length(r) => {
+                let limit = r.limit();
+                match r.fill_buf() {
+                    Ok(buf) if buf.is_empty() && limit > 0 => Err(incomplete_body()),
+                    res => res.map(|buf| buf.is_empty()),
+                }
+            }
+            Inner::Close(r) => r.fill_buf().map(|buf| buf.is_empty()),
+            Inner::Released => Ok(true),
         }
+        .map_err(|e| self.discard(e))?;
+        if is_empty {
+            self.release_if_done();
+            return Ok(&[]);
+        }
+
+        let buf = match &mut self.inner {
+            Inner::Chunked(r) => r.fill_buf()?,
+            Inner::Length(r) => r.fill_buf()?,
+            Inner::Close(r) => r.fill_buf()?,
+            Inner::Released => &[],
+        };
+        Ok(match &self.throttle {
+            Some(throttle) => &buf[..throttle.limit(buf.len())],
+            None => buf,
+        })
     }
 
     #[inline]
     fn consume(&mut self, amt: usize) {
-        match self {
-            BodyReader::Chunked(r) => r.consume(amt),
-            BodyReader::Length(r) => r.consume(amt),
-            BodyReader::Close(r) => r.consume(amt),
+        match &mut self.inner {
+            Inner::Chunked(r) => r.consume(amt),
+            Inner::Length(r) => r.consume(amt),
+            Inner::Close(r) => r.consume(amt),
+            Inner::Released => (),
         }
+        if let Some(throttle) = &mut self.throttle {
+            throttle.consume(amt);
+        }
+        self.release_if_done();
     }
 }
 
@@ -57,9 +173,10 @@ fn is_chunked(headers: &HeaderMap) -> bool {
 }
 
 fn parse_content_length(val: &HeaderValue) -> Result<u64> {
-    let val = val.to_str().map_err(|_| InvalidResponseKind::ContentLength)?;
-    let val = val.parse::<u64>().map_err(|_| InvalidResponseKind::ContentLength)?;
-    Ok(val)
+    val.to_str()
+        .ok()
+        .and_then(|len| len.parse::<u64>().ok())
+        .ok_or_else(|| InvalidResponseKind::ContentLength.at_line(val.as_bytes(), None))
 }
 
 fn is_content_length(headers: &HeaderMap) -> Result<Option<u64>> {
@@ -78,17 +195,55 @@ fn is_content_length(headers: &HeaderMap) -> Result<Option<u64>> {
 }
 
 impl BodyReader {
-    pub fn new(headers: &HeaderMap, reader: BufReader<BaseStream>) -> Result<BodyReader> {
-        if is_chunked(headers) {
+    /// Create a reader for the body described by the headers.
+    ///
+    /// If a pool handle is given, the connection is returned to the pool after the end of the body is reached.
+    ///
+    /// Responses whose length cannot be known for sure are refused, because the data after the body could be
+    /// taken for the next response otherwise. This is the case when `Content-Length` headers have different
+    /// values, or when a `Content-Length` header is sent with the chunked transfer encoding.
+    pub fn new(headers: &HeaderMap, reader: BufReader<BaseStream>, pool: Option<PoolHandle>) -> Result<BodyReader> {
+        if is_chunked(headers) && headers.contains_key(CONTENT_LENGTH) {
+            return Err(InvalidResponseKind::AmbiguousLength.into());
+        }
+
+        let inner = if is_chunked(headers) {
             debug!("creating a chunked body reader");
-            Ok(BodyReader::Chunked(ChunkedReader::new(reader)))
+            Inner::Chunked(ChunkedReader::new(reader))
         } else if let Some(val) = is_content_length(headers)? {
             debug!("creating a length body reader");
-            Ok(BodyReader::Length(reader.take(val)))
+            Inner::Length(reader.take(val))
         } else {
             debug!("creating close reader");
-            Ok(BodyReader::Close(reader))
-        }
+            Inner::Close(reader)
+        };
+        let mut body_reader = BodyReader {
+            inner,
+            pool,
+            throttle: None,
+            trailers: HeaderMap::new(),
+        };
+        // An empty body may never be read, like the one of a redirection.
+        body_reader.release_if_done();
+        Ok(body_reader)
+    }
+
+    /// Limit the rate at which the body is read, if a rate is given.
+    pub fn throttle(mut self, bytes_per_sec: Option<u64>) -> BodyReader {
+        self.throttle = bytes_per_sec.map(Throttle::new);
+        self
+    }
+
+    /// Create a reader for a response which has no body, the connection can be reused right away.
+    pub fn empty(reader: BufReader<BaseStream>, pool: Option<PoolHandle>) -> BodyReader {
+        let mut body_reader = BodyReader {
+            inner: Inner::Length(reader.take(0)),
+            pool,
+            throttle: None,
+            trailers: HeaderMap::new(),
+        };
+        body_reader.release_if_done();
+        body_reader
     }
 }
 
@@ -155,3 +310,71 @@ fn test_is_content_length_many_err() {
     assert_eq!(headers.get_all("content-length").iter().count(), 2);
     assert!(is_content_length(&headers).is_err());
 }
+
+#[test]
+fn test_trailers_kept_after_release() {
+    use crate::pool::{ConnectionPool, PoolKey};
+
+    let url = url::Url::parse("http://google.ca").unwrap();
+    let pool = ConnectionPool::new();
+    let key = PoolKey::new(&url, None, &crate::request::BaseSettings::default()).unwrap();
+    let mut headers = HeaderMap::new();
+    headers.insert("transfer-encoding", HeaderValue::from_static("chunked"));
+    let sock = BaseStream::mock(b"2\r\nok\r\n0\r\nX-Checksum: abc\r\n\r\n".to_vec());
+    let mut reader = BodyReader::new(&headers, BufReader::new(sock), Some(pool.handle(key.clone()))).unwrap();
+
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body).unwrap();
+    assert_eq!(body, b"ok");
+    assert!(pool.checkout(&key).is_some());
+    assert_eq!(reader.trailers()["x-checksum"], "abc");
+}
+
+#[test]
+fn test_empty_body_released_unread() {
+    use crate::pool::{ConnectionPool, PoolKey};
+
+    let url = url::Url::parse("http://google.ca").unwrap();
+    let pool = ConnectionPool::new();
+    let key = PoolKey::new(&url, None, &crate::request::BaseSettings::default()).unwrap();
+    let mut headers = HeaderMap::new();
+    headers.insert("content-length", HeaderValue::from_static("0"));
+    let sock = BaseStream::mock(Vec::new());
+    let _reader = BodyReader::new(&headers, BufReader::new(sock), Some(pool.handle(key.clone()))).unwrap();
+
+    assert!(pool.checkout(&key).is_some());
+}
+
+#[test]
+fn test_chunked_with_content_length_refused() {
+    let mut headers = HeaderMap::new();
+    headers.insert("transfer-encoding", HeaderValue::from_static("chunked"));
+    headers.insert("content-length", HeaderValue::from_static("2"));
+    let sock = BaseStream::mock(b"2\r\nok\r\n0\r\n\r\n".to_vec());
+
+    let err = BodyReader::new(&headers, BufReader::new(sock), None).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::InvalidResponse {
+            kind: InvalidResponseKind::AmbiguousLength,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_conflicting_content_lengths_refused() {
+    let mut headers = HeaderMap::new();
+    headers.append("content-length", HeaderValue::from_static("2"));
+    headers.append("content-length", HeaderValue::from_static("20"));
+    let sock = BaseStream::mock(b"ok".to_vec());
+
+    let err = BodyReader::new(&headers, BufReader::new(sock), None).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::InvalidResponse {
+            kind: InvalidResponseKind::ContentLength,
+            ..
+        }
+    ));
+}
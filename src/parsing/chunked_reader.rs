@@ -1,17 +1,22 @@
 use std::cmp;
 use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
 use std::str;
 
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+
 use crate::error::InvalidResponseKind;
-use crate::parsing::buffers;
+use crate::parsing::buffers::{self, trim_byte};
 
 fn parse_chunk_size(line: &[u8]) -> io::Result<usize> {
-    line.iter()
-        .position(|&b| b == b';')
-        .map_or_else(|| str::from_utf8(line), |idx| str::from_utf8(&line[..idx]))
-        .map_err(|_| InvalidResponseKind::ChunkSize)
-        .and_then(|line| usize::from_str_radix(line.trim(), 16).map_err(|_| InvalidResponseKind::ChunkSize))
-        .map_err(|e| e.into())
+    let size = match line.iter().position(|&b| b == b';') {
+        Some(idx) => &line[..idx],
+        None => line,
+    };
+    str::from_utf8(size)
+        .ok()
+        .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+        .ok_or_else(|| InvalidResponseKind::ChunkSize.at_line(line, None).into())
 }
 
 #[derive(Debug)]
@@ -24,6 +29,7 @@ where
     consumed: usize,  // bytes consumed from `buffer`
     remaining: usize, // bytes remaining until next chunk
     reached_eof: bool,
+    trailers: HeaderMap,
 }
 
 impl<R> ChunkedReader<R>
@@ -37,9 +43,28 @@ where
             consumed: 0,
             remaining: 0,
             reached_eof: false,
+            trailers: HeaderMap::new(),
         }
     }
 
+    /// Check if the last chunk was read and entirely consumed.
+    pub fn is_done(&self) -> bool {
+        self.reached_eof && self.remaining == 0 && self.consumed == self.buffer.len()
+    }
+
+    pub fn into_inner(self) -> BufReader<R> {
+        self.inner
+    }
+
+    /// Get the trailer fields sent after the last chunk, which are empty until the last chunk is read.
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
+    pub fn take_trailers(&mut self) -> HeaderMap {
+        mem::take(&mut self.trailers)
+    }
+
     fn read_chunk_size(&mut self) -> io::Result<usize> {
         buffers::read_line(&mut self.inner, &mut self.buffer, 128)?;
         if self.buffer.is_empty() {
@@ -47,6 +72,40 @@ where
         }
         parse_chunk_size(&self.buffer)
     }
+
+    /// Read the trailer fields sent after the last chunk, up to the empty line ending the body.
+    ///
+    /// Invalid trailer fields are dropped, like invalid headers.
+    fn read_trailers(&mut self) -> io::Result<()> {
+        const MAX_TRAILERS_LEN: usize = 64 * 1024;
+
+        let mut line = Vec::new();
+        let mut len = 0;
+        loop {
+            len += buffers::read_line(&mut self.inner, &mut line, 16 * 1024)?;
+            if line.is_empty() {
+                return Ok(());
+            } else if len > MAX_TRAILERS_LEN {
+                return Err(InvalidResponseKind::Header.into());
+            }
+
+            let col = match line.iter().position(|&c| c == b':') {
+                Some(col) => col,
+                None => {
+                    warn!("Dropped invalid trailer without a colon");
+                    continue;
+                }
+            };
+            let name = HeaderName::from_bytes(trim_byte(b' ', &line[..col]));
+            let value = HeaderValue::from_bytes(trim_byte(b' ', trim_byte(b'\t', &line[col + 1..])));
+            match (name, value) {
+                (Ok(name), Ok(value)) => {
+                    self.trailers.append(name, value);
+                }
+                _ => warn!("Dropped invalid trailer"),
+            }
+        }
+    }
 }
 
 impl<R> BufRead for ChunkedReader<R>
@@ -61,6 +120,10 @@ where
                 self.remaining = self.read_chunk_size()?;
                 if self.remaining == 0 {
                     self.reached_eof = true;
+                    self.buffer.clear();
+                    self.consumed = 0;
+                    self.read_trailers()?;
+                    return Ok(&[]);
                 }
             }
 
@@ -105,6 +168,56 @@ fn test_read_works() {
     assert_eq!(s, "wikipedia in\r\n\r\nchunks.");
 }
 
+#[test]
+fn test_read_chunk_extensions() {
+    let msg = b"5;foo=bar\r\nhello\r\n6;name=\"quoted;value\";x\r\n world\r\n0;last\r\n\r\n";
+    let mut reader = ChunkedReader::new(BufReader::new(&msg[..]));
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "hello world");
+}
+
+#[test]
+fn test_read_trailers() {
+    let msg = b"4\r\nwiki\r\n5\r\npedia\r\n0\r\nExpires: never\r\nX-Checksum: abc\r\n\r\nnext";
+    let mut reader = ChunkedReader::new(BufReader::new(&msg[..]));
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "wikipedia");
+    assert!(reader.is_done());
+
+    let mut inner = reader.into_inner();
+    let mut rest = String::new();
+    inner.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "next");
+}
+
+#[test]
+fn test_read_trailers_values() {
+    let msg = b"4\r\nwiki\r\n0\r\nExpires: never\r\nX-Checksum:abc\r\nx-checksum: def\r\ninvalid\r\n\r\n";
+    let mut reader = ChunkedReader::new(BufReader::new(&msg[..]));
+    assert!(reader.trailers().is_empty());
+
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    let trailers = reader.trailers();
+    assert_eq!(trailers.len(), 3);
+    assert_eq!(trailers["expires"], "never");
+    let checksums: Vec<_> = trailers.get_all("x-checksum").iter().collect();
+    assert_eq!(checksums, ["abc", "def"]);
+}
+
+#[test]
+fn test_read_invalid_unterminated_trailers() {
+    let msg = b"4\r\nwiki\r\n0\r\nExpires: never\r\n";
+    let mut reader = ChunkedReader::new(BufReader::new(&msg[..]));
+    let mut s = String::new();
+    assert_eq!(
+        reader.read_to_string(&mut s).err().unwrap().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
 #[test]
 fn test_read_empty() {
     let msg = b"0\r\n\r\n";
@@ -133,6 +246,15 @@ fn test_read_invalid_chunk() {
     );
 }
 
+#[test]
+fn test_read_invalid_chunk_size() {
+    let msg = b"zz\r\nwiki\r\n0\r\n\r\n";
+    let mut reader = ChunkedReader::new(BufReader::new(&msg[..]));
+    let mut s = String::new();
+    let err = crate::Error::from(reader.read_to_string(&mut s).unwrap_err());
+    assert_eq!(err.to_string(), "Invalid response: invalid chunk size: \"zz\"");
+}
+
 #[test]
 fn test_read_invalid_no_terminating_chunk() {
     let msg = b"4\r\nwiki";
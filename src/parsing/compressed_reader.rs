@@ -1,7 +1,11 @@
+#[cfg(feature = "brotli")]
+use std::fmt;
+#[cfg(feature = "compress")]
+use std::io::BufRead;
 use std::io::{self, Read};
 
 #[cfg(feature = "compress")]
-use flate2::bufread::{DeflateDecoder, GzDecoder};
+use flate2::bufread::{DeflateDecoder, GzDecoder, ZlibDecoder};
 use http::header::HeaderMap;
 #[cfg(feature = "compress")]
 use http::header::{CONTENT_ENCODING, TRANSFER_ENCODING};
@@ -18,7 +22,22 @@ pub enum CompressedReader {
     #[cfg(feature = "compress")]
     Deflate(DeflateDecoder<BodyReader>),
     #[cfg(feature = "compress")]
+    Zlib(ZlibDecoder<BodyReader>),
+    #[cfg(feature = "compress")]
     Gzip(GzDecoder<BodyReader>),
+    #[cfg(feature = "brotli")]
+    Brotli(BrotliDecoder),
+}
+
+/// A Brotli decoder, which does not implement `Debug` itself.
+#[cfg(feature = "brotli")]
+pub struct BrotliDecoder(Box<brotli_decompressor::Decompressor<BodyReader>>);
+
+#[cfg(feature = "brotli")]
+impl fmt::Debug for BrotliDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BrotliDecoder")
+    }
 }
 
 #[cfg(feature = "compress")]
@@ -49,19 +68,68 @@ fn have_encoding(headers: &HeaderMap, enc: &str) -> bool {
     have_encoding_content_encoding(headers, enc) || have_encoding_transfer_encoding(headers, enc)
 }
 
+/// Check if a `deflate` body starts with a zlib header.
+///
+/// The `deflate` encoding is defined as a zlib stream, but some servers send a raw deflate stream instead. A zlib
+/// header uses the deflate method with a window of at most 32 KiB, and its first two bytes are a multiple of 31.
+#[cfg(feature = "compress")]
+fn is_zlib(start: &[u8]) -> bool {
+    match *start {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && cmf >> 4 <= 7 && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0,
+        [cmf] => cmf & 0x0f == 8 && cmf >> 4 <= 7,
+        [] => false,
+    }
+}
+
+/// Check if a body is empty, waiting for its first bytes only if its length is unknown.
+#[cfg(feature = "compress")]
+fn is_empty(reader: &mut BodyReader) -> io::Result<bool> {
+    match reader.remaining_len() {
+        Some(len) => Ok(len == 0),
+        None => Ok(reader.fill_buf()?.is_empty()),
+    }
+}
+
 impl CompressedReader {
     #[cfg(feature = "compress")]
-    pub fn new<B>(headers: &HeaderMap, request: &PreparedRequest<B>, reader: BodyReader) -> Result<CompressedReader> {
+    pub fn new<B>(
+        headers: &HeaderMap,
+        request: &PreparedRequest<B>,
+        mut reader: BodyReader,
+    ) -> Result<CompressedReader> {
         if request.method() != Method::HEAD {
-            if have_encoding(headers, "gzip") {
+            let gzip = have_encoding(headers, "gzip");
+            let deflate = !gzip && have_encoding(headers, "deflate");
+            #[cfg(feature = "brotli")]
+            let brotli = !gzip && !deflate && have_encoding(headers, "br");
+            #[cfg(not(feature = "brotli"))]
+            let brotli = false;
+            // An empty body is not a valid compressed stream, even if the server says it is compressed.
+            if (gzip || deflate || brotli) && is_empty(&mut reader)? {
+                debug!("creating plain reader for an empty body");
+                return Ok(CompressedReader::Plain(reader));
+            }
+
+            if gzip {
                 debug!("creating gzip decoder");
                 return Ok(CompressedReader::Gzip(GzDecoder::new(reader)));
             }
 
-            if have_encoding(headers, "deflate") {
+            if deflate {
+                if is_zlib(reader.fill_buf()?) {
+                    debug!("creating zlib decoder");
+                    return Ok(CompressedReader::Zlib(ZlibDecoder::new(reader)));
+                }
                 debug!("creating deflate decoder");
                 return Ok(CompressedReader::Deflate(DeflateDecoder::new(reader)));
             }
+
+            #[cfg(feature = "brotli")]
+            if brotli {
+                debug!("creating brotli decoder");
+                let decoder = brotli_decompressor::Decompressor::new(reader, 4096);
+                return Ok(CompressedReader::Brotli(BrotliDecoder(Box::new(decoder))));
+            }
         }
         debug!("creating plain reader");
         Ok(CompressedReader::Plain(reader))
@@ -71,6 +139,35 @@ impl CompressedReader {
     pub fn new<B>(_: &HeaderMap, _: &PreparedRequest<B>, reader: BodyReader) -> Result<CompressedReader> {
         Ok(CompressedReader::Plain(reader))
     }
+
+    /// Get the number of bytes left to read, if it is known.
+    pub fn remaining_len(&self) -> Option<u64> {
+        match self {
+            CompressedReader::Plain(r) => r.remaining_len(),
+            #[cfg(feature = "compress")]
+            _ => None,
+        }
+    }
+
+    /// Get the trailer fields sent after a chunked body.
+    pub fn trailers(&self) -> &HeaderMap {
+        match self {
+            CompressedReader::Plain(r) => r.trailers(),
+            #[cfg(feature = "compress")]
+            CompressedReader::Deflate(r) => r.get_ref().trailers(),
+            #[cfg(feature = "compress")]
+            CompressedReader::Zlib(r) => r.get_ref().trailers(),
+            #[cfg(feature = "compress")]
+            CompressedReader::Gzip(r) => r.get_ref().trailers(),
+            #[cfg(feature = "brotli")]
+            CompressedReader::Brotli(r) => r.0.get_ref().trailers(),
+        }
+    }
+
+    /// Check if the body is decompressed while it is read.
+    pub fn is_decompressing(&self) -> bool {
+        !matches!(self, CompressedReader::Plain(_))
+    }
 }
 
 impl Read for CompressedReader {
@@ -82,7 +179,11 @@ impl Read for CompressedReader {
             #[cfg(feature = "compress")]
             CompressedReader::Deflate(s) => s.read(buf),
             #[cfg(feature = "compress")]
+            CompressedReader::Zlib(s) => s.read(buf),
+            #[cfg(feature = "compress")]
             CompressedReader::Gzip(s) => s.read(buf),
+            #[cfg(feature = "brotli")]
+            CompressedReader::Brotli(s) => s.0.read(buf),
         }
     }
 }
@@ -90,18 +191,20 @@ impl Read for CompressedReader {
 #[cfg(test)]
 mod tests {
     use std::io::prelude::*;
+    use std::io::BufReader;
 
     #[cfg(feature = "compress")]
     use flate2::{
-        write::{DeflateEncoder, GzEncoder},
+        write::{DeflateEncoder, GzEncoder, ZlibEncoder},
         Compression,
     };
+    use http::header::CONTENT_LENGTH;
     #[cfg(feature = "compress")]
-    use http::header::{HeaderMap, HeaderValue};
+    use http::header::{HeaderMap, HeaderValue, CONTENT_ENCODING};
     use http::Method;
 
     #[cfg(feature = "compress")]
-    use super::have_encoding;
+    use super::{have_encoding, is_zlib};
     use crate::parsing::response::parse_response;
     use crate::streams::BaseStream;
     use crate::PreparedRequest;
@@ -157,7 +260,8 @@ mod tests {
         let req = PreparedRequest::new(Method::GET, "http://google.ca");
 
         let sock = BaseStream::mock(buf);
-        let response = parse_response(sock, &req).unwrap();
+        let response = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
+        assert_eq!(response.headers()[CONTENT_LENGTH], "19");
         assert_eq!(response.text().unwrap(), "Hello world!!!!!!!!");
     }
 
@@ -180,10 +284,56 @@ mod tests {
         let req = PreparedRequest::new(Method::GET, "http://google.ca");
 
         let sock = BaseStream::mock(buf);
-        let response = parse_response(sock, &req).unwrap();
+        let response = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+        assert!(!response.headers().contains_key(CONTENT_LENGTH));
+        assert_eq!(response.text().unwrap(), "Hello world!!!!!!!!");
+    }
+
+    #[test]
+    #[cfg(feature = "compress")]
+    fn test_stream_zlib() {
+        let mut payload = Vec::new();
+        let mut enc = ZlibEncoder::new(&mut payload, Compression::default());
+        enc.write_all(b"Hello world!!!!!!!!").unwrap();
+        enc.finish().unwrap();
+
+        let mut buf: Vec<u8> = Vec::new();
+        let _ = write!(
+            buf,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Encoding: deflate\r\n\r\n",
+            payload.len()
+        );
+        buf.extend(payload);
+
+        let req = PreparedRequest::new(Method::GET, "http://google.ca");
+
+        let sock = BaseStream::mock(buf);
+        let response = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
         assert_eq!(response.text().unwrap(), "Hello world!!!!!!!!");
     }
 
+    #[test]
+    #[cfg(feature = "compress")]
+    fn test_is_zlib() {
+        for level in 0..=9 {
+            let mut payload = Vec::new();
+            let mut enc = ZlibEncoder::new(&mut payload, Compression::new(level));
+            enc.write_all(b"Hello world!!!!!!!!").unwrap();
+            enc.finish().unwrap();
+            assert!(is_zlib(&payload));
+
+            let mut payload = Vec::new();
+            let mut enc = DeflateEncoder::new(&mut payload, Compression::new(level));
+            enc.write_all(b"Hello world!!!!!!!!").unwrap();
+            enc.finish().unwrap();
+            assert!(!is_zlib(&payload));
+        }
+        assert!(is_zlib(&[0x78]));
+        assert!(!is_zlib(&[]));
+    }
+
     #[test]
     #[cfg(feature = "compress")]
     fn test_stream_gzip() {
@@ -203,8 +353,34 @@ mod tests {
         let req = PreparedRequest::new(Method::GET, "http://google.ca");
 
         let sock = BaseStream::mock(buf);
-        let response = parse_response(sock, &req).unwrap();
+        let response = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
+
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+        assert!(!response.headers().contains_key(CONTENT_LENGTH));
+        assert_eq!(response.text().unwrap(), "Hello world!!!!!!!!");
+    }
+
+    #[test]
+    #[cfg(feature = "brotli")]
+    fn test_stream_brotli() {
+        // "Hello world!!!!!!!!" compressed with Brotli.
+        let payload = b"\x0b\x09\x80Hello world!!!!!!!!\x03";
+
+        let mut buf: Vec<u8> = Vec::new();
+        let _ = write!(
+            buf,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Encoding: br\r\n\r\n",
+            payload.len()
+        );
+        buf.extend(&payload[..]);
+
+        let req = PreparedRequest::new(Method::GET, "http://google.ca");
+
+        let sock = BaseStream::mock(buf);
+        let response = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
 
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+        assert!(!response.headers().contains_key(CONTENT_LENGTH));
         assert_eq!(response.text().unwrap(), "Hello world!!!!!!!!");
     }
 
@@ -216,7 +392,48 @@ mod tests {
         let req = PreparedRequest::new(Method::GET, "http://google.ca");
         let sock = BaseStream::mock(buf.to_vec());
         // Fixed by the move from libflate to flate2
-        assert!(parse_response(sock, &req).is_ok());
+        assert!(parse_response(BufReader::new(sock), &req, req.url(), None).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "compress")]
+    fn test_empty_body_with_encoding() {
+        for buf in [
+            &b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 0\r\n\r\n"[..],
+            b"HTTP/1.1 200 OK\r\nContent-Encoding: deflate\r\nContent-Length: 0\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n",
+        ] {
+            let req = PreparedRequest::new(Method::GET, "http://google.ca");
+            let sock = BaseStream::mock(buf.to_vec());
+            let response = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
+            assert_eq!(response.text().unwrap(), "");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "brotli")]
+    fn test_empty_body_with_brotli() {
+        let buf = b"HTTP/1.1 200 OK\r\nContent-Encoding: br\r\nContent-Length: 0\r\n\r\n";
+        let req = PreparedRequest::new(Method::GET, "http://google.ca");
+        let sock = BaseStream::mock(buf.to_vec());
+        let response = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
+        assert_eq!(response.text().unwrap(), "");
+    }
+
+    #[test]
+    #[cfg(feature = "compress")]
+    fn test_no_body_with_gzip_status() {
+        for buf in [
+            &b"HTTP/1.1 304 Not Modified\r\nContent-Encoding: gzip\r\nContent-Length: 42\r\n\r\n"[..],
+            b"HTTP/1.1 204 No Content\r\nContent-Encoding: gzip\r\n\r\n",
+            b"HTTP/1.1 101 Switching Protocols\r\nContent-Encoding: gzip\r\nUpgrade: websocket\r\n\r\n",
+        ] {
+            let req = PreparedRequest::new(Method::GET, "http://google.ca");
+            let sock = BaseStream::mock(buf.to_vec());
+            let response = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
+            assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+            assert_eq!(response.text().unwrap(), "");
+        }
     }
 
     #[test]
@@ -226,6 +443,8 @@ mod tests {
 
         let req = PreparedRequest::new(Method::HEAD, "http://google.ca");
         let sock = BaseStream::mock(buf.to_vec());
-        assert!(parse_response(sock, &req).is_ok());
+        let response = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(response.text().unwrap(), "");
     }
 }
@@ -1,16 +1,23 @@
-use std::io::{self, BufReader, Read, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
 use std::str;
+use std::time::Duration;
 
 use http::{
-    header::{HeaderName, HeaderValue, TRANSFER_ENCODING},
-    HeaderMap, StatusCode,
+    header::{
+        HeaderName, HeaderValue, CONNECTION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, LOCATION,
+        TRANSFER_ENCODING,
+    },
+    HeaderMap, Method, StatusCode, Version,
 };
+use url::Url;
 
-use crate::error::{ErrorKind, InvalidResponseKind, Result};
+use crate::error::{Error, ErrorKind, InvalidResponseKind, Result};
 use crate::parsing::buffers::{self, trim_byte};
 use crate::parsing::{body_reader::BodyReader, compressed_reader::CompressedReader, ResponseReader};
-use crate::request::PreparedRequest;
-use crate::streams::BaseStream;
+use crate::pool::PoolHandle;
+use crate::request::{BaseSettings, PreparedRequest};
+use crate::streams::{BaseStream, ConnectionInfo};
 
 #[cfg(feature = "charsets")]
 use crate::{charsets::Charset, parsing::TextReader};
@@ -18,75 +25,337 @@ use crate::{charsets::Charset, parsing::TextReader};
 #[cfg(feature = "json")]
 use serde::de::DeserializeOwned;
 
-pub fn parse_response_head<R>(reader: &mut BufReader<R>, max_headers: usize) -> Result<(StatusCode, HeaderMap)>
+/// The status line and the headers of a response.
+#[derive(Debug)]
+pub struct ResponseHead {
+    pub version: Version,
+    pub status: StatusCode,
+    pub reason: Option<String>,
+    pub headers: HeaderMap,
+    pub raw: Option<Vec<u8>>,
+}
+
+/// Append a line read in `n` bytes to the raw head, with the line ending it was read with.
+fn capture_line(raw: &mut Option<Vec<u8>>, line: &[u8], n: usize) {
+    if let Some(raw) = raw {
+        raw.extend_from_slice(line);
+        raw.extend_from_slice(if n - line.len() == 2 { b"\r\n" } else { b"\n" });
+    }
+}
+
+/// Split the first token of a line separated by spaces from the rest of the line.
+fn split_token(buf: &[u8]) -> (&[u8], &[u8]) {
+    let buf = buffers::trim_byte_left(b' ', buf);
+    match buf.iter().position(|&b| b == b' ') {
+        Some(pos) => (&buf[..pos], &buf[pos + 1..]),
+        None => (buf, &[]),
+    }
+}
+
+const MAX_LINE_LEN: u64 = 16 * 1024;
+
+/// Report a head cut off by the end of the stream as incomplete once a part of it was `received`.
+///
+/// A stream which ends before any byte of the head keeps its IO error, the server closed the connection without
+/// answering. A line which reached the maximum line length is not cut off by the end of the stream.
+fn incomplete_head(err: io::Error, line: &[u8], received: bool) -> Error {
+    if err.kind() == io::ErrorKind::UnexpectedEof && received && (line.len() as u64) < MAX_LINE_LEN {
+        ErrorKind::IncompleteResponse.into()
+    } else {
+        err.into()
+    }
+}
+
+/// Read the head of a response, made of at most `max_headers` headers and `max_header_size` bytes.
+///
+/// Header lines folded onto the next line, the obsolete line folding of RFC 7230, are rejected unless the
+/// `lenient` setting is enabled, in which case they are joined to the value of the header with a space. The
+/// `lenient` setting also accepts header lines ending with a bare LF and matches the HTTP version regardless of
+/// its case.
+pub fn parse_response_head<R>(reader: &mut BufReader<R>, settings: &BaseSettings) -> Result<ResponseHead>
 where
     R: Read,
 {
-    const MAX_LINE_LEN: u64 = 16 * 1024;
-
     let mut line = Vec::new();
     let mut headers = HeaderMap::new();
+    let mut head_len = 0;
+    let mut raw = if settings.capture_raw { Some(Vec::new()) } else { None };
 
     // status line
-    let status: StatusCode = {
-        buffers::read_line(reader, &mut line, MAX_LINE_LEN)?;
-        let mut parts = line.split(|&b| b == b' ').filter(|x| !x.is_empty());
+    let (version, status, reason) = {
+        head_len += buffers::read_line(reader, &mut line, MAX_LINE_LEN)
+            .map_err(|err| incomplete_head(err, &line, !line.is_empty()))?;
+        capture_line(&mut raw, &line, head_len);
+        if head_len > settings.max_header_size {
+            return Err(ErrorKind::HeadersTooLarge.into());
+        }
+        let (version, rest) = split_token(&line);
+        let (code, reason) = split_token(rest);
+        if version.is_empty() || code.is_empty() {
+            return Err(InvalidResponseKind::StatusLine.at_line(&line, Some(0)));
+        }
 
-        let _ = parts.next().ok_or(InvalidResponseKind::StatusLine)?;
-        let code = parts.next().ok_or(InvalidResponseKind::StatusLine)?;
+        // Unknown versions are treated like HTTP/1.0, which does not keep connections alive.
+        let version = match version {
+            b"HTTP/1.1" => Version::HTTP_11,
+            b"HTTP/0.9" => Version::HTTP_09,
+            v if settings.lenient && v.eq_ignore_ascii_case(b"HTTP/1.1") => Version::HTTP_11,
+            v if settings.lenient && v.eq_ignore_ascii_case(b"HTTP/0.9") => Version::HTTP_09,
+            _ => Version::HTTP_10,
+        };
 
-        str::from_utf8(code)
-            .map_err(|_| InvalidResponseKind::StatusCode)?
-            .parse()
-            .map_err(|_| InvalidResponseKind::StatusCode)?
+        let status = str::from_utf8(code)
+            .ok()
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| InvalidResponseKind::StatusCode.at_line(&line, Some(0)))?;
+
+        // The reason phrase may be empty, or contain bytes which are not UTF-8.
+        let reason = match trim_byte(b' ', reason) {
+            [] => None,
+            reason => Some(String::from_utf8_lossy(reason).into_owned()),
+        };
+
+        (version, status, reason)
     };
 
-    // headers
+    // headers, the last one is kept aside in case the next line continues it
+    let mut pending: Option<(HeaderName, Vec<u8>)> = None;
     loop {
-        buffers::read_line_strict(reader, &mut line, MAX_LINE_LEN)?;
+        let n = if settings.lenient {
+            buffers::read_line(reader, &mut line, MAX_LINE_LEN)
+        } else {
+            buffers::read_line_strict(reader, &mut line, MAX_LINE_LEN)
+        }
+        .map_err(|err| incomplete_head(err, &line, true))?;
+        capture_line(&mut raw, &line, n);
+        let offset = Some(head_len as u64);
+        head_len += n;
+        if head_len > settings.max_header_size {
+            return Err(ErrorKind::HeadersTooLarge.into());
+        }
+
+        let folded = line.starts_with(b" ") || line.starts_with(b"\t");
+        // With bare LF line endings, a value split by LF is joined like it is within a line ending with CRLF.
+        let split = settings.lenient && pending.is_some() && !line.is_empty() && !line.contains(&b':');
+        if folded || split {
+            if !settings.lenient {
+                return Err(InvalidResponseKind::FoldedHeader.at_line(&line, offset));
+            }
+            match &mut pending {
+                Some((_, value)) => {
+                    value.push(b' ');
+                    value.extend_from_slice(trim_whitespace(&line));
+                }
+                None => warn!("Dropped a folded line without a header"),
+            }
+            continue;
+        }
+
+        if let Some((header, value)) = pending.take() {
+            headers.append(header, HeaderValue::from_bytes(&value).map_err(http::Error::from)?);
+        }
+
         if line.is_empty() {
             break;
-        } else if headers.len() == max_headers {
-            return Err(InvalidResponseKind::Header.into());
+        } else if headers.len() == settings.max_headers {
+            return Err(InvalidResponseKind::Header.at_line(&line, offset));
         }
 
         let col = line
             .iter()
             .position(|&c| c == b':')
-            .ok_or(InvalidResponseKind::Header)?;
+            .ok_or_else(|| InvalidResponseKind::Header.at_line(&line, offset))?;
 
         buffers::replace_byte(b'\n', b' ', &mut line[col + 1..]);
 
         let header = trim_byte(b' ', &line[..col]);
-        let value = trim_byte(b' ', &line[col + 1..]);
+        let value = trim_whitespace(&line[col + 1..]);
 
-        let header = match HeaderName::from_bytes(header) {
-            Ok(val) => val,
-            Err(err) => {
-                warn!("Dropped invalid response header: {}", err);
-                continue;
-            }
-        };
+        match HeaderName::from_bytes(header) {
+            Ok(header) => pending = Some((header, value.to_vec())),
+            Err(err) => warn!("Dropped invalid response header: {}", err),
+        }
+    }
+
+    Ok(ResponseHead {
+        version,
+        status,
+        reason,
+        headers,
+        raw,
+    })
+}
+
+/// Trim the spaces and tabs around a header value.
+fn trim_whitespace(buf: &[u8]) -> &[u8] {
+    let is_whitespace = |b: &u8| *b == b' ' || *b == b'\t';
+    let start = buf.iter().position(|b| !is_whitespace(b)).unwrap_or(buf.len());
+    let end = buf.iter().rposition(|b| !is_whitespace(b)).map_or(start, |n| n + 1);
+    &buf[start..end]
+}
+
+/// Check if the server lets the connection stay open after this response.
+fn is_keep_alive(version: Version, headers: &HeaderMap) -> bool {
+    let mut tokens = headers
+        .get_all(CONNECTION)
+        .into_iter()
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .map(|s| s.trim());
+
+    match version {
+        Version::HTTP_11 => !tokens.any(|s| s.eq_ignore_ascii_case("close")),
+        _ => tokens.any(|s| s.eq_ignore_ascii_case("keep-alive")),
+    }
+}
+
+/// Wait for the server to accept the body of a request sent with `Expect: 100-continue`.
+///
+/// Returns `None` once the body can be sent, either because the server sent `100 Continue` or because it did
+/// not answer within `timeout`. Returns the head of the final response if the server answers without waiting
+/// for the body.
+pub fn wait_for_continue(
+    reader: &mut BufReader<BaseStream>,
+    timeout: Duration,
+    settings: &BaseSettings,
+) -> Result<Option<ResponseHead>> {
+    reader.get_ref().set_read_timeout(timeout)?;
+    let answered = match reader.fill_buf() {
+        Ok(_) => true,
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => false,
+        Err(err) => return Err(err.into()),
+    };
+    reader.get_ref().set_read_timeout(settings.read_timeout)?;
+
+    if !answered {
+        debug!("no interim response received, sending the body");
+        return Ok(None);
+    }
+
+    loop {
+        let head = parse_response_head(reader, settings)?;
+        if head.status == StatusCode::CONTINUE {
+            return Ok(None);
+        } else if !is_interim(head.status) {
+            return Ok(Some(head));
+        }
+        debug!("skipping interim response {}", head.status);
+    }
+}
+
+/// Check if the status is sent before the final response, `101 Switching Protocols` being the final response of
+/// an upgrade.
+fn is_interim(status: StatusCode) -> bool {
+    status.is_informational() && status != StatusCode::SWITCHING_PROTOCOLS
+}
+
+/// Read the head of the final response, skipping any interim response sent before it.
+pub fn parse_final_response_head<R>(reader: &mut BufReader<R>, settings: &BaseSettings) -> Result<ResponseHead>
+where
+    R: Read,
+{
+    loop {
+        let head = parse_response_head(reader, settings)?;
+        if !is_interim(head.status) {
+            return Ok(head);
+        }
+        debug!("skipping interim response {}", head.status);
+    }
+}
 
-        headers.append(header, HeaderValue::from_bytes(value).map_err(http::Error::from)?);
+/// Read the response to a request sent to `url`, which differs from the URL of the request after a redirection.
+pub fn parse_response<B>(
+    mut reader: BufReader<BaseStream>,
+    request: &PreparedRequest<B>,
+    url: &Url,
+    pool: Option<PoolHandle>,
+) -> Result<Response> {
+    let head = parse_final_response_head(&mut reader, &request.base_settings)?;
+    response_from_head(head, reader, request, url, pool)
+}
+
+/// Parse a `Content-Range` header value such as `bytes 0-499/1234`.
+fn parse_content_range(value: &str) -> Option<(u64, u64, Option<u64>)> {
+    let value = value.trim();
+    if value.len() < 6 || !value[..6].eq_ignore_ascii_case("bytes ") {
+        return None;
     }
 
-    Ok((status, headers))
+    let mut parts = value[6..].trim_start().splitn(2, '/');
+    let mut range = parts.next()?.splitn(2, '-');
+    let start = range.next()?.trim().parse().ok()?;
+    let end = range.next()?.trim().parse().ok()?;
+    let len = match parts.next()?.trim() {
+        "*" => None,
+        len => Some(len.parse().ok()?),
+    };
+
+    if start > end || len.is_some_and(|len| end >= len) {
+        return None;
+    }
+    Some((start, end, len))
+}
+
+/// Check if a response can have a body, whatever its headers say.
+fn has_body(method: &Method, status: StatusCode) -> bool {
+    method != Method::HEAD && status != StatusCode::NO_CONTENT && status != StatusCode::NOT_MODIFIED
 }
 
-pub fn parse_response<B>(reader: BaseStream, request: &PreparedRequest<B>) -> Result<Response> {
-    let mut reader = BufReader::new(reader);
-    let (status, mut headers) = parse_response_head(&mut reader, request.base_settings.max_headers)?;
-    let body_reader = BodyReader::new(&headers, reader)?;
-    let compressed_reader = CompressedReader::new(&headers, request, body_reader)?;
+/// Create a `Response` from its parsed head, the body being read from `reader`.
+pub fn response_from_head<B>(
+    head: ResponseHead,
+    reader: BufReader<BaseStream>,
+    request: &PreparedRequest<B>,
+    url: &Url,
+    pool: Option<PoolHandle>,
+) -> Result<Response> {
+    let ResponseHead {
+        version,
+        status,
+        reason,
+        mut headers,
+        raw,
+    } = head;
+    let connection_info = reader.get_ref().connection_info();
+    let pool = pool.filter(|_| is_keep_alive(version, &headers));
+    let compressed_reader = if has_body(request.method(), status) {
+        let body_reader = BodyReader::new(&headers, reader, pool)?.throttle(request.base_settings.max_bandwidth);
+        let max_body_size = request.base_settings.max_body_size;
+        if let (Some(max), Some(len)) = (max_body_size, body_reader.remaining_len()) {
+            if len > max {
+                return Err(ErrorKind::BodyTooLarge.into());
+            }
+        }
+        if status.is_informational() {
+            // The data following `101 Switching Protocols` belongs to the new protocol, it is never decompressed.
+            CompressedReader::Plain(body_reader)
+        } else {
+            CompressedReader::new(&headers, request, body_reader)?
+        }
+    } else {
+        debug!("the response has no body");
+        CompressedReader::Plain(BodyReader::empty(reader, pool))
+    };
+
+    // The decompressed body is no longer described by those headers.
+    if compressed_reader.is_decompressing() {
+        headers.remove(CONTENT_ENCODING);
+        headers.remove(CONTENT_LENGTH);
+    }
+
     let response_reader = ResponseReader::new(&headers, request, compressed_reader);
 
     // Remove HOP-BY-HOP headers
     headers.remove(TRANSFER_ENCODING);
 
     Ok(Response {
+        url: url.clone(),
+        history: Vec::new(),
         status,
+        reason,
         headers,
+        raw_head: raw,
+        connection_info,
         reader: response_reader,
     })
 }
@@ -94,30 +363,143 @@ pub fn parse_response<B>(reader: BaseStream, request: &PreparedRequest<B>) -> Re
 /// `Response` represents a response returned by a server.
 #[derive(Debug)]
 pub struct Response {
+    url: Url,
+    history: Vec<(StatusCode, Url)>,
     status: StatusCode,
+    reason: Option<String>,
     headers: HeaderMap,
+    raw_head: Option<Vec<u8>>,
+    connection_info: ConnectionInfo,
     reader: ResponseReader,
 }
 
 impl Response {
+    /// Get the URL this `Response` was received from, which is the URL of the last redirection followed.
+    ///
+    /// Relative links found in the body of the response are resolved against this URL.
+    #[inline]
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Get the redirections followed before receiving this `Response`, as the status of each redirection and the
+    /// URL which sent it.
+    ///
+    /// The history is only recorded if it was enabled with `record_history`, it is empty otherwise.
+    #[inline]
+    pub fn history(&self) -> &[(StatusCode, Url)] {
+        &self.history
+    }
+
+    pub(crate) fn with_history(mut self, history: Vec<(StatusCode, Url)>) -> Response {
+        self.history = history;
+        self
+    }
+
     /// Get the status code of this `Response`.
     #[inline]
     pub fn status(&self) -> StatusCode {
         self.status
     }
 
+    /// Get the reason phrase sent after the status code, such as `Not Found`, or `None` if it is empty.
+    ///
+    /// The reason phrase is only informative and may differ from the canonical reason of the status code. Bytes
+    /// which are not valid UTF-8 are replaced.
+    #[inline]
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
     /// Get the headers of this `Response`.
     #[inline]
     pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 
+    /// Get the exact bytes of the status line and headers of this `Response`, up to the empty line ending them.
+    ///
+    /// They are only kept if enabled with `capture_raw`, `None` is returned otherwise. Interim responses such as
+    /// `100 Continue` are not included.
+    #[inline]
+    pub fn raw_head(&self) -> Option<&[u8]> {
+        self.raw_head.as_deref()
+    }
+
+    /// Get details about the connection this `Response` was received on, such as the address of the server and
+    /// the TLS session.
+    #[inline]
+    pub fn connection_info(&self) -> &ConnectionInfo {
+        &self.connection_info
+    }
+
+    /// Get the trailer fields sent after a chunked body, such as a checksum announced by a `Trailer` header.
+    ///
+    /// The trailers are only known once the whole body has been read through this `Response`, they are empty
+    /// until then. Use `ResponseReader::trailers` after `split` to consume the body otherwise.
+    #[inline]
+    pub fn trailers(&self) -> &HeaderMap {
+        self.reader.trailers()
+    }
+
     /// Checks if the status code of this `Response` was a success code.
     #[inline]
     pub fn is_success(&self) -> bool {
         self.status.is_success()
     }
 
+    /// Checks if this `Response` is `304 Not Modified`, sent for a conditional request when the resource did not
+    /// change. Such a response never has a body.
+    #[inline]
+    pub fn is_not_modified(&self) -> bool {
+        self.status == StatusCode::NOT_MODIFIED
+    }
+
+    /// Checks if this `Response` is `206 Partial Content`, meaning that only the requested range of the body is sent.
+    #[inline]
+    pub fn is_partial(&self) -> bool {
+        self.status == StatusCode::PARTIAL_CONTENT
+    }
+
+    /// Get the range of the body sent in this `Response`, from its `Content-Range` header.
+    ///
+    /// The range is returned as the offsets of its first and last bytes, followed by the length of the whole
+    /// body if the server knows it. `None` is returned if the header is missing or invalid.
+    pub fn content_range(&self) -> Option<(u64, u64, Option<u64>)> {
+        let value = self.headers.get(CONTENT_RANGE)?.to_str().ok()?;
+        parse_content_range(value)
+    }
+
+    /// Get the length of the body of this `Response`, from its `Content-Length` header.
+    ///
+    /// This is the length of the body as it is sent by the server. The header is removed when the body is
+    /// decompressed, since it no longer describes it, and `None` is returned then. `None` is also returned if the
+    /// header is missing or invalid.
+    pub fn content_length(&self) -> Option<u64> {
+        self.headers.get(CONTENT_LENGTH)?.to_str().ok()?.trim().parse().ok()
+    }
+
+    /// Get the media type of the body of this `Response`, from its `Content-Type` header.
+    ///
+    /// `None` is returned if the header is missing or invalid. This method requires the `mime` feature.
+    #[cfg(feature = "mime")]
+    pub fn content_type(&self) -> Option<mime::Mime> {
+        self.headers
+            .get(http::header::CONTENT_TYPE)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Get the value of the `Location` header of this `Response`, such as the target of a redirection.
+    ///
+    /// The location is returned as sent by the server, it may be relative to the URL of the response. `None` is
+    /// returned if the header is missing or not visible ASCII.
+    pub fn location(&self) -> Option<&str> {
+        self.headers.get(LOCATION)?.to_str().ok()
+    }
+
     /// Returns error variant if the status code was not a success code.
     pub fn error_for_status(self) -> Result<Self> {
         if self.is_success() {
@@ -144,6 +526,31 @@ impl Response {
         self.reader.write_to(writer)
     }
 
+    /// Write the response to any object that implements `Write`, reporting the progress of the transfer.
+    ///
+    /// `progress` is called every time a block of data is written with the number of bytes written so
+    /// far and the total length of the body, if the server sent a `Content-Length` and the body is not
+    /// decompressed.
+    #[inline]
+    pub fn write_to_with_progress<W, F>(self, writer: W, progress: F) -> Result<u64>
+    where
+        W: Write,
+        F: FnMut(u64, Option<u64>),
+    {
+        self.reader.write_to_with_progress(writer, progress)
+    }
+
+    /// Write the response to a file, returning the number of bytes written.
+    ///
+    /// The file is created if it does not exist and truncated if it does.
+    #[inline]
+    pub fn write_to_file<P>(self, path: P) -> Result<u64>
+    where
+        P: AsRef<Path>,
+    {
+        self.reader.write_to_file(path)
+    }
+
     /// Read the response to a `Vec` of bytes.
     #[inline]
     pub fn bytes(self) -> Result<Vec<u8>> {
@@ -251,11 +658,20 @@ impl Read for Response {
     }
 }
 
+#[cfg(test)]
+fn head_settings(max_headers: usize, max_header_size: usize) -> BaseSettings {
+    BaseSettings {
+        max_headers,
+        max_header_size,
+        ..BaseSettings::default()
+    }
+}
+
 #[test]
 fn test_read_request_head() {
     let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nContent-Type: text/plain\r\n\r\nhello";
     let mut reader = BufReader::new(&response[..]);
-    let (status, headers) = parse_response_head(&mut reader, 100).unwrap();
+    let ResponseHead { status, headers, .. } = parse_response_head(&mut reader, &BaseSettings::default()).unwrap();
     assert_eq!(status, StatusCode::OK);
     assert_eq!(headers.len(), 2);
     assert_eq!(headers[http::header::CONTENT_LENGTH], "5");
@@ -266,20 +682,418 @@ fn test_read_request_head() {
 fn test_line_folded_header() {
     let response = b"HTTP/1.1 200 OK\r\nheader-of-great-many-lines: foo\nbar\nbaz\nqux\r\nthe-other-kind-of-header: foobar\r\n\r\n";
     let mut reader = BufReader::new(&response[..]);
-    let (status, headers) = parse_response_head(&mut reader, 100).unwrap();
+    let ResponseHead { status, headers, .. } = parse_response_head(&mut reader, &BaseSettings::default()).unwrap();
     assert_eq!(status, StatusCode::OK);
     assert_eq!(headers.len(), 2);
     assert_eq!(headers["header-of-great-many-lines"], "foo bar baz qux");
     assert_eq!(headers["the-other-kind-of-header"], "foobar");
 }
 
+#[test]
+fn test_obs_fold_rejected() {
+    let response = b"HTTP/1.1 200 OK\r\nx-folded: foo\r\n  bar\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let err = parse_response_head(&mut reader, &BaseSettings::default()).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::InvalidResponse {
+            kind: InvalidResponseKind::FoldedHeader,
+            ..
+        }
+    ));
+
+    // A folded line containing a colon is not mistaken for a new header.
+    let response = b"HTTP/1.1 200 OK\r\nx-folded: foo\r\n bar: baz\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    assert!(parse_response_head(&mut reader, &BaseSettings::default()).is_err());
+}
+
+#[test]
+fn test_obs_fold_lenient() {
+    let settings = BaseSettings {
+        lenient: true,
+        ..BaseSettings::default()
+    };
+    let response =
+        b"HTTP/1.1 200 OK\r\n x-orphan: foo\r\nx-folded: foo\r\n  bar\r\n\tbar: baz \r\nx-other: qux\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let headers = parse_response_head(&mut reader, &settings).unwrap().headers;
+    assert_eq!(headers.len(), 2);
+    assert_eq!(headers["x-folded"], "foo bar bar: baz");
+    assert_eq!(headers["x-other"], "qux");
+}
+
+#[test]
+fn test_lenient_bare_lf() {
+    let settings = BaseSettings {
+        lenient: true,
+        ..BaseSettings::default()
+    };
+    let response = b"http/1.1 200\nx-foo: bar\nx-split: foo\nbar\r\n\nbody";
+    let mut reader = BufReader::new(&response[..]);
+    let head = parse_response_head(&mut reader, &settings).unwrap();
+    assert_eq!(head.version, Version::HTTP_11);
+    assert_eq!(head.status, StatusCode::OK);
+    assert_eq!(head.reason, None);
+    assert_eq!(head.headers["x-foo"], "bar");
+    assert_eq!(head.headers["x-split"], "foo bar");
+
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "body");
+}
+
+#[test]
+fn test_strict_bare_lf() {
+    // The whole head is read as a single line which never ends.
+    let response = b"HTTP/1.1 200 OK\nx-foo: bar\n\n";
+    let mut reader = BufReader::new(&response[..]);
+    assert!(parse_response_head(&mut reader, &BaseSettings::default()).is_err());
+
+    let response = b"http/1.1 200 OK\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let head = parse_response_head(&mut reader, &BaseSettings::default()).unwrap();
+    assert_eq!(head.version, Version::HTTP_10);
+}
+
+#[test]
+fn test_header_without_space() {
+    let response = b"HTTP/1.1 200 OK\r\nx-foo:bar\r\nx-bar:\tbaz\t\r\nx-empty:\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let headers = parse_response_head(&mut reader, &BaseSettings::default())
+        .unwrap()
+        .headers;
+    assert_eq!(headers["x-foo"], "bar");
+    assert_eq!(headers["x-bar"], "baz");
+    assert_eq!(headers["x-empty"], "");
+}
+
+#[test]
+fn test_invalid_header_error_details() {
+    let response = b"HTTP/1.1 200 OK\r\nx-foo: bar\r\nnot a header\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let err = parse_response_head(&mut reader, &BaseSettings::default()).unwrap_err();
+    match err.kind() {
+        ErrorKind::InvalidResponse { kind, line, offset } => {
+            assert!(matches!(kind, InvalidResponseKind::Header));
+            assert_eq!(line.as_deref(), Some(&b"not a header"[..]));
+            assert_eq!(*offset, Some(29));
+        }
+        kind => panic!("unexpected error {:?}", kind),
+    }
+    assert_eq!(
+        err.to_string(),
+        "Invalid response: invalid header at byte 29: \"not a header\""
+    );
+}
+
 #[test]
 fn test_max_headers_limit() {
     let response = b"HTTP/1.1 200 OK\r\nfirst-header: foo\r\nsecond-header: bar\r\none-header-too-many: baz\r\n\r\n";
     let mut reader = BufReader::new(&response[..]);
-    let err = parse_response_head(&mut reader, 2).unwrap_err();
+    let err = parse_response_head(&mut reader, &head_settings(2, 64 * 1024)).unwrap_err();
     assert!(matches!(
         err.kind(),
-        ErrorKind::InvalidResponse(InvalidResponseKind::Header)
+        ErrorKind::InvalidResponse {
+            kind: InvalidResponseKind::Header,
+            ..
+        }
     ));
 }
+
+#[test]
+fn test_max_header_size_limit() {
+    let response = b"HTTP/1.1 200 OK\r\nfirst-header: foo\r\nsecond-header: bar\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    assert!(parse_response_head(&mut reader, &head_settings(100, response.len())).is_ok());
+
+    let mut reader = BufReader::new(&response[..]);
+    let err = parse_response_head(&mut reader, &head_settings(100, response.len() - 1)).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::HeadersTooLarge));
+}
+
+#[test]
+fn test_max_header_size_invalid_headers() {
+    // Dropped headers count towards the limit.
+    let mut response = b"HTTP/1.1 200 OK\r\n".to_vec();
+    for _ in 0..1000 {
+        response.extend_from_slice(b"invalid header: foo\r\n");
+    }
+    response.extend_from_slice(b"\r\n");
+    let mut reader = BufReader::new(&response[..]);
+    let err = parse_response_head(&mut reader, &head_settings(100, 1024)).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::HeadersTooLarge));
+}
+
+#[test]
+fn test_read_request_head_version() {
+    let response = b"HTTP/1.0 200 OK\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let version = parse_response_head(&mut reader, &BaseSettings::default())
+        .unwrap()
+        .version;
+    assert_eq!(version, Version::HTTP_10);
+}
+
+#[test]
+fn test_read_request_head_reason() {
+    let reason = |response: &[u8]| {
+        let mut reader = BufReader::new(response);
+        parse_response_head(&mut reader, &BaseSettings::default())
+            .unwrap()
+            .reason
+    };
+    assert_eq!(reason(b"HTTP/1.1 404 Not Found\r\n\r\n").as_deref(), Some("Not Found"));
+    assert_eq!(reason(b"HTTP/1.1 200  Very  OK \r\n\r\n").as_deref(), Some("Very  OK"));
+    assert_eq!(reason(b"HTTP/1.1 200 \xffOK\r\n\r\n").as_deref(), Some("\u{fffd}OK"));
+    assert_eq!(reason(b"HTTP/1.1 200 \r\n\r\n"), None);
+    assert_eq!(reason(b"HTTP/1.1 200\r\n\r\n"), None);
+}
+
+#[test]
+fn test_read_request_head_invalid_status_line() {
+    let mut reader = BufReader::new(&b"HTTP/1.1\r\n\r\n"[..]);
+    let err = parse_response_head(&mut reader, &BaseSettings::default()).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::InvalidResponse {
+            kind: InvalidResponseKind::StatusLine,
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Invalid response: invalid status line at byte 0: \"HTTP/1.1\""
+    );
+}
+
+#[test]
+fn test_is_keep_alive() {
+    let mut headers = HeaderMap::new();
+    assert!(is_keep_alive(Version::HTTP_11, &headers));
+    assert!(!is_keep_alive(Version::HTTP_10, &headers));
+
+    headers.insert(CONNECTION, HeaderValue::from_static("Keep-Alive"));
+    assert!(is_keep_alive(Version::HTTP_10, &headers));
+
+    headers.insert(CONNECTION, HeaderValue::from_static("upgrade, close"));
+    assert!(!is_keep_alive(Version::HTTP_11, &headers));
+}
+
+#[test]
+fn test_skip_interim_responses() {
+    let response = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+    let mut reader = BufReader::new(&response[..]);
+    let ResponseHead { status, headers, .. } =
+        parse_final_response_head(&mut reader, &BaseSettings::default()).unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers[http::header::CONTENT_LENGTH], "5");
+}
+
+#[test]
+fn test_switching_protocols_is_final() {
+    let response = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n";
+    let mut reader = BufReader::new(&response[..]);
+    let status = parse_final_response_head(&mut reader, &BaseSettings::default())
+        .unwrap()
+        .status;
+    assert_eq!(status, StatusCode::SWITCHING_PROTOCOLS);
+}
+
+#[cfg(test)]
+fn mock_response(method: Method, response: &[u8]) -> Response {
+    let req = PreparedRequest::new(method, "http://google.ca");
+    let sock = BaseStream::mock(response.to_vec());
+    parse_response(BufReader::new(sock), &req, req.url(), None).unwrap()
+}
+
+#[test]
+fn test_head_response_has_no_body() {
+    let resp = mock_response(Method::HEAD, b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n");
+    assert_eq!(resp.headers()[CONTENT_LENGTH], "5");
+    assert_eq!(resp.bytes().unwrap(), b"");
+}
+
+#[test]
+fn test_head_response_releases_connection() {
+    use crate::pool::{ConnectionPool, PoolKey};
+
+    let req = PreparedRequest::new(Method::HEAD, "http://google.ca");
+    let pool = ConnectionPool::new();
+    let key = PoolKey::new(req.url(), None, &req.base_settings).unwrap();
+    let sock = BaseStream::mock(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n".to_vec());
+    let resp = parse_response(BufReader::new(sock), &req, req.url(), Some(pool.handle(key.clone()))).unwrap();
+
+    assert!(pool.checkout(&key).is_some());
+    drop(resp);
+}
+
+#[test]
+fn test_no_content_response_has_no_body() {
+    let resp = mock_response(
+        Method::GET,
+        b"HTTP/1.1 204 No Content\r\nContent-Length: 5\r\n\r\nhello",
+    );
+    assert_eq!(resp.bytes().unwrap(), b"");
+}
+
+#[test]
+fn test_not_modified_response_has_no_body() {
+    let resp = mock_response(
+        Method::GET,
+        b"HTTP/1.1 304 Not Modified\r\nTransfer-Encoding: chunked\r\n\r\n",
+    );
+    assert!(resp.is_not_modified());
+    assert_eq!(resp.bytes().unwrap(), b"");
+}
+
+#[test]
+fn test_parse_content_range() {
+    assert_eq!(parse_content_range("bytes 0-499/1234"), Some((0, 499, Some(1234))));
+    assert_eq!(parse_content_range("bytes 500-1233/*"), Some((500, 1233, None)));
+    assert_eq!(parse_content_range("bytes */1234"), None);
+    assert_eq!(parse_content_range("bytes 10-5/1234"), None);
+    assert_eq!(parse_content_range("bytes 0-1234/1234"), None);
+    assert_eq!(parse_content_range("items 0-4/5"), None);
+}
+
+#[test]
+fn test_partial_content_response() {
+    let resp = mock_response(
+        Method::GET,
+        b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 6-10/11\r\nContent-Length: 5\r\n\r\nworld",
+    );
+    assert!(resp.is_partial());
+    assert_eq!(resp.content_range(), Some((6, 10, Some(11))));
+    assert_eq!(resp.bytes().unwrap(), b"world");
+}
+
+#[test]
+fn test_range_ignored_by_server() {
+    let resp = mock_response(Method::GET, b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world");
+    assert!(!resp.is_partial());
+    assert_eq!(resp.content_range(), None);
+}
+
+#[test]
+fn test_typed_headers() {
+    let resp = mock_response(
+        Method::GET,
+        b"HTTP/1.1 201 Created\r\nContent-Length: 5\r\nContent-Type: text/plain; charset=utf-8\r\nLocation: /items/1\r\n\r\nhello",
+    );
+    assert_eq!(resp.content_length(), Some(5));
+    #[cfg(feature = "mime")]
+    assert_eq!(resp.content_type(), Some(mime::TEXT_PLAIN_UTF_8));
+    assert_eq!(resp.location(), Some("/items/1"));
+}
+
+#[test]
+fn test_typed_headers_missing_or_invalid() {
+    let resp = mock_response(
+        Method::GET,
+        b"HTTP/1.1 200 OK\r\nContent-Type: not a mime\r\nLocation: caf\xc3\xa9\r\nConnection: close\r\n\r\nhello",
+    );
+    assert_eq!(resp.content_length(), None);
+    #[cfg(feature = "mime")]
+    assert_eq!(resp.content_type(), None);
+    assert_eq!(resp.location(), None);
+}
+
+#[test]
+fn test_response_trailers() {
+    let mut resp = mock_response(
+        Method::GET,
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: Expires\r\n\r\n5\r\nhello\r\n0\r\nExpires: Wed, 21 Oct 2015 07:28:00 GMT\r\n\r\n",
+    );
+    assert!(resp.trailers().is_empty());
+
+    let mut body = String::new();
+    resp.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "hello");
+    assert_eq!(resp.trailers()["expires"], "Wed, 21 Oct 2015 07:28:00 GMT");
+    assert_eq!(resp.trailers().len(), 1);
+}
+
+#[test]
+fn test_raw_head_disabled() {
+    let resp = mock_response(Method::GET, b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+    assert!(resp.raw_head().is_none());
+}
+
+#[test]
+fn test_raw_head() {
+    let head = b"HTTP/1.1 200 OK\r\nX-B:  1\r\nx-a: 2\r\nX-B: 3\r\nContent-Length: 5\r\n\r\n";
+    let response = [&head[..], b"hello"].concat();
+
+    let settings = BaseSettings {
+        capture_raw: true,
+        ..BaseSettings::default()
+    };
+    let mut reader = BufReader::new(&response[..]);
+    let parsed = parse_response_head(&mut reader, &settings).unwrap();
+    assert_eq!(parsed.raw.as_deref(), Some(&head[..]));
+
+    let mut req = PreparedRequest::new(Method::GET, "http://google.ca");
+    req.base_settings.capture_raw = true;
+    let sock = BaseStream::mock([&b"HTTP/1.1 100 Continue\r\n\r\n"[..], &response].concat());
+    let resp = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap();
+    assert_eq!(resp.raw_head(), Some(&head[..]));
+    assert_eq!(resp.text().unwrap(), "hello");
+}
+
+#[test]
+fn test_raw_head_bare_lf() {
+    let head = b"HTTP/1.1 200 OK\nX-Folded: a\n b\n\n";
+
+    let settings = BaseSettings {
+        capture_raw: true,
+        lenient: true,
+        ..BaseSettings::default()
+    };
+    let mut reader = BufReader::new(&head[..]);
+    let parsed = parse_response_head(&mut reader, &settings).unwrap();
+    assert_eq!(parsed.raw.as_deref(), Some(&head[..]));
+    assert_eq!(parsed.headers["x-folded"], "a b");
+}
+
+#[test]
+fn test_incomplete_head() {
+    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+    let head_len = response.len() - 5;
+
+    for &lenient in &[false, true] {
+        let settings = BaseSettings {
+            lenient,
+            ..BaseSettings::default()
+        };
+        for end in 1..head_len {
+            let mut reader = BufReader::new(&response[..end]);
+            let err = parse_response_head(&mut reader, &settings).unwrap_err();
+            assert!(
+                matches!(err.kind(), ErrorKind::IncompleteResponse),
+                "cut at {}: {}",
+                end,
+                err
+            );
+        }
+        let mut reader = BufReader::new(&response[..head_len]);
+        assert!(parse_response_head(&mut reader, &settings).is_ok());
+    }
+}
+
+#[test]
+fn test_incomplete_head_nothing_received() {
+    let mut reader = BufReader::new(&b""[..]);
+    let err = parse_response_head(&mut reader, &BaseSettings::default()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn test_incomplete_head_after_interim() {
+    let req = PreparedRequest::new(Method::GET, "http://google.ca");
+    let sock = BaseStream::mock(b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Le".to_vec());
+    let err = parse_response(BufReader::new(sock), &req, req.url(), None).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::IncompleteResponse));
+    assert_eq!(err.to_string(), "Connection closed before the end of the response head");
+}
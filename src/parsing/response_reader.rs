@@ -1,12 +1,16 @@
+use std::fs::File;
 #[cfg(any(feature = "charsets", feature = "json"))]
 use std::io::BufReader;
 use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use http::header::HeaderMap;
 #[cfg(feature = "json")]
 use serde::de::DeserializeOwned;
 
-use crate::error::Result;
+use crate::error::{Error, ErrorKind, Result};
 use crate::parsing::compressed_reader::CompressedReader;
 use crate::request::PreparedRequest;
 
@@ -21,35 +25,56 @@ use {
     http::header::CONTENT_TYPE,
 };
 
+/// Get the value of the `charset` parameter of a media type, such as `text/html; charset="utf-8"`.
 #[cfg(feature = "charsets")]
-fn get_charset(headers: &HeaderMap, default_charset: Option<Charset>) -> Charset {
-    if let Some(value) = headers.get(CONTENT_TYPE) {
-        let bytes = value.as_bytes();
-        if let Some(scol) = bytes.iter().position(|&b| b == b';') {
-            let rhs = trim_byte(b' ', &bytes[scol + 1..]);
-            if rhs.starts_with(b"charset=") {
-                if let Some(enc) = Encoding::for_label(&rhs[8..]) {
-                    return enc;
-                }
-            }
+fn charset_param(media_type: &[u8]) -> Option<&[u8]> {
+    media_type.split(|&b| b == b';').skip(1).find_map(|param| {
+        let eq = param.iter().position(|&b| b == b'=')?;
+        let name = trim_byte(b' ', trim_byte(b'\t', &param[..eq]));
+        if !name.eq_ignore_ascii_case(b"charset") {
+            return None;
         }
-    }
-    default_charset.unwrap_or(charsets::WINDOWS_1252)
+        let value = trim_byte(b' ', trim_byte(b'\t', &param[eq + 1..]));
+        match value {
+            [b'"', quoted @ .., b'"'] => Some(quoted),
+            _ => Some(value),
+        }
+    })
+}
+
+/// Get the charset of the body from the `Content-Type` header, falling back to the default charset and then to
+/// `WINDOWS_1252` when it is missing or unknown.
+#[cfg(feature = "charsets")]
+fn get_charset(headers: &HeaderMap, default_charset: Option<Charset>) -> Charset {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| charset_param(value.as_bytes()))
+        .and_then(Encoding::for_label)
+        .or(default_charset)
+        .unwrap_or(charsets::WINDOWS_1252)
 }
 
 /// The `ResponseReader` is used to read the body of a response.
 ///
 /// The `ResponseReader` implements `Read` and can be used like any other stream,
-/// but the data returned by `Read` are untouched bytes from the socket. This means
-/// that if a string is expected back, it could be in a different encoding than the
-/// expected one. In order to properly read text, use the `charsets` feature and the
-/// `text` or `text_reader` methods.
+/// for instance to process a large body without buffering it in memory.
+///
+/// `Read` only returns the bytes of the body. The chunked transfer encoding and, if the
+/// `compress` feature is enabled, the content encoding are decoded. `read` returns 0 once
+/// the body ends, that is after `Content-Length` bytes, after the last chunk, or when the
+/// server closes the connection if the body has no length. Data sent after the body is never
+/// returned. A connection closed before the end of the body is reported as an error, of kind
+/// `ErrorKind::IncompleteBody` for a body with a `Content-Length`.
 ///
-/// In general it's best to avoid `Read`ing directly from this object. Instead use the
-/// helper methods, they process the data stream properly.
+/// The bytes are not decoded from their charset. This means that if a string is expected
+/// back, it could be in a different encoding than the expected one. In order to properly
+/// read text, use the `charsets` feature and the `text` or `text_reader` methods.
 #[derive(Debug)]
 pub struct ResponseReader {
     inner: CompressedReader,
+    max_body_size: Option<u64>,
+    read_len: u64,
+    cancel_token: Option<Arc<AtomicBool>>,
     #[cfg(feature = "charsets")]
     charset: Charset,
 }
@@ -63,13 +88,38 @@ impl ResponseReader {
     ) -> ResponseReader {
         ResponseReader {
             inner: reader,
-            charset: get_charset(&headers, request.base_settings.default_charset),
+            max_body_size: request.base_settings.max_body_size,
+            read_len: 0,
+            cancel_token: request.base_settings.cancel_token.clone(),
+            charset: get_charset(headers, request.base_settings.default_charset),
         }
     }
 
     #[cfg(not(feature = "charsets"))]
-    pub(crate) fn new<B>(_: &HeaderMap, _: &PreparedRequest<B>, reader: CompressedReader) -> ResponseReader {
-        ResponseReader { inner: reader }
+    pub(crate) fn new<B>(_: &HeaderMap, request: &PreparedRequest<B>, reader: CompressedReader) -> ResponseReader {
+        ResponseReader {
+            inner: reader,
+            max_body_size: request.base_settings.max_body_size,
+            read_len: 0,
+            cancel_token: request.base_settings.cancel_token.clone(),
+        }
+    }
+
+    /// Get the capacity to reserve before reading the whole body.
+    ///
+    /// The `Content-Length` sent by the server is trusted up to a limit, to avoid allocating too much memory up front.
+    fn capacity_hint(&self) -> usize {
+        const MAX_CAPACITY_HINT: u64 = 16 * 1024 * 1024;
+        self.inner
+            .remaining_len()
+            .map_or(0, |len| len.min(MAX_CAPACITY_HINT) as usize)
+    }
+
+    /// Get the trailer fields sent after a chunked body.
+    ///
+    /// The trailers are only known once the whole body has been read, they are empty until then.
+    pub fn trailers(&self) -> &HeaderMap {
+        self.inner.trailers()
     }
 
     /// Write the response to any object that implements `Write`.
@@ -77,13 +127,51 @@ impl ResponseReader {
     where
         W: Write,
     {
-        let n = io::copy(&mut self.inner, &mut writer)?;
+        let n = io::copy(&mut self, &mut writer)?;
         Ok(n)
     }
 
+    /// Write the response to any object that implements `Write`, reporting the progress of the transfer.
+    ///
+    /// `progress` is called every time a block of data is written with the number of bytes written so
+    /// far and the total length of the body, if the server sent a `Content-Length` and the body is not
+    /// decompressed.
+    pub fn write_to_with_progress<W, F>(mut self, mut writer: W, mut progress: F) -> Result<u64>
+    where
+        W: Write,
+        F: FnMut(u64, Option<u64>),
+    {
+        let total = self.inner.remaining_len();
+        let mut buf = [0; 16 * 1024];
+        let mut written = 0;
+
+        loop {
+            let n = match self.read(&mut buf) {
+                Ok(0) => return Ok(written),
+                Ok(n) => n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+            writer.write_all(&buf[..n])?;
+            written += n as u64;
+            progress(written, total);
+        }
+    }
+
+    /// Write the response to a file, returning the number of bytes written.
+    ///
+    /// The file is created if it does not exist and truncated if it does.
+    pub fn write_to_file<P>(self, path: P) -> Result<u64>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::create(path)?;
+        self.write_to(file)
+    }
+
     /// Read the response to a `Vec` of bytes.
     pub fn bytes(self) -> Result<Vec<u8>> {
-        let mut buf = Vec::new();
+        let mut buf = Vec::with_capacity(self.capacity_hint());
         self.write_to(&mut buf)?;
         Ok(buf)
     }
@@ -166,8 +254,8 @@ impl ResponseReader {
     /// Note that this is lossy, i.e. it will not raise errors when
     /// invalid data is encountered but output replacement characters instead.
     pub fn text_utf8(mut self) -> Result<String> {
-        let mut buf = Vec::new();
-        self.inner.read_to_end(&mut buf)?;
+        let mut buf = Vec::with_capacity(self.capacity_hint());
+        self.read_to_end(&mut buf)?;
 
         let text = String::from_utf8(buf).unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned());
 
@@ -231,10 +319,127 @@ impl ResponseReader {
 impl Read for ResponseReader {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.read(buf)
+        if self
+            .cancel_token
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+        {
+            return Err(io::Error::other(Error::from(ErrorKind::Cancelled)));
+        }
+        let n = self.inner.read(buf)?;
+        self.read_len += n as u64;
+        if self.max_body_size.is_some_and(|max| self.read_len > max) {
+            return Err(io::Error::other(Error::from(ErrorKind::BodyTooLarge)));
+        }
+        Ok(n)
     }
 }
 
+#[cfg(test)]
+fn mock_response_reader(response: &[u8]) -> ResponseReader {
+    use crate::parsing::parse_response;
+    use crate::streams::BaseStream;
+
+    let req = PreparedRequest::new(http::Method::GET, "http://google.ca");
+    let sock = BaseStream::mock(response.to_vec());
+    let response = parse_response(std::io::BufReader::new(sock), &req, req.url(), None).unwrap();
+    response.split().2
+}
+
+#[test]
+fn test_capacity_hint_content_length() {
+    let reader = mock_response_reader(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    assert_eq!(reader.capacity_hint(), 5);
+    assert_eq!(reader.bytes().unwrap(), b"hello");
+}
+
+#[test]
+fn test_capacity_hint_huge_content_length() {
+    let reader = mock_response_reader(b"HTTP/1.1 200 OK\r\nContent-Length: 10000000000\r\n\r\n");
+    assert_eq!(reader.capacity_hint(), 16 * 1024 * 1024);
+}
+
+#[test]
+fn test_capacity_hint_chunked() {
+    let reader = mock_response_reader(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n");
+    assert_eq!(reader.capacity_hint(), 0);
+    assert_eq!(reader.bytes().unwrap(), b"hello");
+}
+
+#[test]
+fn test_write_to_file() {
+    let path = std::env::temp_dir().join(format!("attohttpc-write-to-file-{}", std::process::id()));
+    std::fs::write(&path, "previous content").unwrap();
+
+    let reader = mock_response_reader(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let res = reader.write_to_file(&path);
+    let content = std::fs::read(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(res.unwrap(), 5);
+    assert_eq!(content.unwrap(), b"hello");
+}
+
+#[test]
+fn test_write_to_with_progress() {
+    let reader = mock_response_reader(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let mut calls = Vec::new();
+    let mut buf = Vec::new();
+    let n = reader
+        .write_to_with_progress(&mut buf, |written, total| calls.push((written, total)))
+        .unwrap();
+    assert_eq!(n, 5);
+    assert_eq!(buf, b"hello");
+    assert_eq!(calls, vec![(5, Some(5))]);
+}
+
+#[test]
+fn test_write_to_with_progress_chunked() {
+    let reader = mock_response_reader(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+    );
+    let mut calls = Vec::new();
+    let mut buf = Vec::new();
+    reader
+        .write_to_with_progress(&mut buf, |written, total| calls.push((written, total)))
+        .unwrap();
+    assert_eq!(buf, b"hello world");
+    assert_eq!(calls.last(), Some(&(11, None)));
+}
+
+#[test]
+fn test_read_stops_at_content_length() {
+    let mut reader = mock_response_reader(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhelloHTTP/1.1 200 OK\r\n");
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"hello");
+    assert_eq!(reader.read(&mut [0; 16]).unwrap(), 0);
+}
+
+#[test]
+fn test_read_truncated_content_length() {
+    let reader = mock_response_reader(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello");
+    let err = reader.bytes().unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::IncompleteBody));
+
+    let mut reader = mock_response_reader(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello");
+    let mut buf = Vec::new();
+    let err = reader.read_to_end(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(buf, b"hello");
+}
+
+#[test]
+fn test_read_stops_after_last_chunk() {
+    let mut reader = mock_response_reader(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\nHTTP/1.1 200 OK\r\n",
+    );
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"hello");
+    assert_eq!(reader.read(&mut [0; 16]).unwrap(), 0);
+}
+
 #[cfg(test)]
 #[cfg(feature = "charsets")]
 mod tests {
@@ -263,6 +468,32 @@ mod tests {
         assert_eq!(get_charset(&headers, None), charsets::UTF_8);
     }
 
+    #[test]
+    fn test_get_charset_from_header_variants() {
+        for value in &[
+            "text/html;charset=utf-8",
+            "text/html; CHARSET=UTF-8",
+            "text/html; charset=\"utf-8\"",
+            "text/html; format=flowed; charset=utf-8",
+            "text/html; charset = \"utf-8\" ; format=flowed",
+        ] {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_str(value).unwrap());
+            assert_eq!(get_charset(&headers, None), charsets::UTF_8, "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_get_charset_unknown() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html; charset=bogus"));
+        assert_eq!(get_charset(&headers, Some(charsets::UTF_16LE)), charsets::UTF_16LE);
+        assert_eq!(get_charset(&headers, None), charsets::WINDOWS_1252);
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html; format=flowed"));
+        assert_eq!(get_charset(&headers, Some(charsets::UTF_8)), charsets::UTF_8);
+    }
+
     #[test]
     fn test_get_charset_from_default() {
         let headers = HeaderMap::new();
@@ -275,3 +506,56 @@ mod tests {
         assert_eq!(get_charset(&headers, None), charsets::WINDOWS_1252);
     }
 }
+
+#[cfg(test)]
+fn mock_response_reader_with_max_body_size(response: &[u8], max_body_size: u64) -> Result<ResponseReader> {
+    use crate::parsing::parse_response;
+    use crate::request::RequestBuilder;
+    use crate::streams::BaseStream;
+
+    let req = RequestBuilder::new(http::Method::GET, "http://google.ca")
+        .max_body_size(max_body_size)
+        .prepare();
+    let sock = BaseStream::mock(response.to_vec());
+    let response = parse_response(std::io::BufReader::new(sock), &req, req.url(), None)?;
+    Ok(response.split().2)
+}
+
+#[test]
+fn test_max_body_size() {
+    let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+    let reader = mock_response_reader_with_max_body_size(response, 5).unwrap();
+    assert_eq!(reader.bytes().unwrap(), b"hello");
+
+    let reader = mock_response_reader_with_max_body_size(response, 4).unwrap();
+    let err = reader.bytes().unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::BodyTooLarge));
+}
+
+#[test]
+fn test_max_body_size_content_length() {
+    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+    let err = mock_response_reader_with_max_body_size(response, 4).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::BodyTooLarge));
+}
+
+#[test]
+fn test_max_bandwidth() {
+    use crate::parsing::parse_response;
+    use crate::request::RequestBuilder;
+    use crate::streams::BaseStream;
+    use std::time::{Duration, Instant};
+
+    let mut response = b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n".to_vec();
+    response.extend_from_slice(&[b'a'; 1000]);
+
+    let req = RequestBuilder::new(http::Method::GET, "http://localhost")
+        .max_bandwidth(2000)
+        .prepare();
+    let sock = BaseStream::mock(response);
+    let response = parse_response(std::io::BufReader::new(sock), &req, req.url(), None).unwrap();
+
+    let start = Instant::now();
+    assert_eq!(response.bytes().unwrap().len(), 1000);
+    assert!(start.elapsed() >= Duration::from_millis(350));
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufReader;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::request::BaseSettings;
+use crate::streams::BaseStream;
+use crate::tls::TlsVersion;
+use crate::{ErrorKind, Result};
+
+/// Identifies the connections which can be used interchangeably to send a request.
+///
+/// Besides the server it reaches, a connection is identified by the settings used to open it, a request with
+/// other settings, like one which does not accept invalid certificates, never reuses it.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PoolKey {
+    scheme: String,
+    host: String,
+    port: u16,
+    proxy: Option<Url>,
+    unix_socket: Option<PathBuf>,
+    resolve_overrides: Vec<(String, SocketAddr)>,
+    local_address: Option<IpAddr>,
+    tcp_nodelay: bool,
+    accept_invalid_certs: bool,
+    accept_invalid_hostnames: bool,
+    min_tls_version: TlsVersion,
+    sni_hostname: Option<String>,
+    certificates_id: u64,
+}
+
+impl PoolKey {
+    pub fn new(url: &Url, proxy: Option<&Url>, settings: &BaseSettings) -> Result<PoolKey> {
+        let mut resolve_overrides: Vec<_> = settings
+            .resolve_overrides
+            .iter()
+            .map(|(host, addr)| (host.clone(), *addr))
+            .collect();
+        resolve_overrides.sort();
+
+        Ok(PoolKey {
+            scheme: url.scheme().to_owned(),
+            host: url.host_str().ok_or(ErrorKind::InvalidUrlHost)?.to_owned(),
+            port: url.port_or_known_default().ok_or(ErrorKind::InvalidUrlPort)?,
+            proxy: proxy.cloned(),
+            unix_socket: settings.unix_socket.clone(),
+            resolve_overrides,
+            local_address: settings.local_address,
+            tcp_nodelay: settings.tcp_nodelay,
+            accept_invalid_certs: settings.accept_invalid_certs,
+            accept_invalid_hostnames: settings.accept_invalid_hostnames,
+            min_tls_version: settings.min_tls_version,
+            sni_hostname: settings.sni_hostname.clone(),
+            certificates_id: settings.certificates_id,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct IdleConnection {
+    reader: BufReader<BaseStream>,
+    since: Instant,
+}
+
+#[derive(Debug)]
+struct PoolState {
+    // The idle connections of each key, from the oldest to the most recently used.
+    idle: HashMap<PoolKey, Vec<IdleConnection>>,
+    idle_timeout: Duration,
+    max_idle_per_host: usize,
+}
+
+impl PoolState {
+    /// Close the connections of every key that stayed idle for too long.
+    fn evict_expired(&mut self) {
+        let idle_timeout = self.idle_timeout;
+        self.idle.retain(|_, conns| {
+            conns.retain(|conn| conn.since.elapsed() < idle_timeout);
+            !conns.is_empty()
+        });
+    }
+}
+
+/// A set of idle connections that can be reused by the requests of a `Session`.
+///
+/// Cloning the pool is cheap, every clone shares the same connections.
+#[derive(Clone)]
+pub struct ConnectionPool {
+    state: Arc<Mutex<PoolState>>,
+}
+
+impl ConnectionPool {
+    pub fn new() -> ConnectionPool {
+        ConnectionPool {
+            state: Arc::new(Mutex::new(PoolState {
+                idle: HashMap::new(),
+                idle_timeout: Duration::from_secs(90),
+                max_idle_per_host: 32,
+            })),
+        }
+    }
+
+    /// Create a pool with the pool limits of the given settings.
+    pub fn with_settings(settings: &BaseSettings) -> ConnectionPool {
+        let pool = ConnectionPool::new();
+        pool.set_idle_timeout(settings.pool_idle_timeout);
+        pool.set_max_idle_per_host(settings.pool_max_idle_per_host);
+        pool
+    }
+
+    pub fn set_idle_timeout(&self, idle_timeout: Duration) {
+        self.state.lock().unwrap().idle_timeout = idle_timeout;
+    }
+
+    pub fn set_max_idle_per_host(&self, max_idle_per_host: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.max_idle_per_host = max_idle_per_host;
+        for conns in state.idle.values_mut() {
+            let excess = conns.len().saturating_sub(max_idle_per_host);
+            conns.drain(..excess);
+        }
+        state.idle.retain(|_, conns| !conns.is_empty());
+    }
+
+    /// Take an idle connection for the given key out of the pool, if there is one.
+    ///
+    /// The connections of every key that stayed idle for too long are closed, there is no background thread
+    /// doing it. Connections closed by the server while they were idle are skipped.
+    pub fn checkout(&self, key: &PoolKey) -> Option<BufReader<BaseStream>> {
+        let mut state = self.state.lock().unwrap();
+        state.evict_expired();
+        let conns = state.idle.get_mut(key)?;
+
+        let mut found = None;
+        while let Some(conn) = conns.pop() {
+            if conn.reader.get_ref().is_idle_alive() {
+                found = Some(conn.reader);
+                break;
+            }
+            debug!("discarding pooled connection closed by the server");
+        }
+        if conns.is_empty() {
+            state.idle.remove(key);
+        }
+
+        found
+    }
+
+    /// Put a connection back into the pool.
+    ///
+    /// The oldest idle connection of the key is closed if there are already too many.
+    pub fn checkin(&self, key: PoolKey, reader: BufReader<BaseStream>) {
+        // Data left in the buffer does not belong to any response we know of,
+        // the connection cannot be used for another request.
+        if !reader.buffer().is_empty() {
+            debug!("discarding connection with unexpected data");
+            return;
+        }
+
+        debug!("returning connection to the pool");
+
+        let mut state = self.state.lock().unwrap();
+        state.evict_expired();
+        let max_idle_per_host = state.max_idle_per_host;
+        if max_idle_per_host == 0 {
+            return;
+        }
+
+        let conns = state.idle.entry(key).or_default();
+        if conns.len() >= max_idle_per_host {
+            debug!("closing the oldest idle connection");
+            conns.drain(..=conns.len() - max_idle_per_host);
+        }
+        conns.push(IdleConnection {
+            reader,
+            since: Instant::now(),
+        });
+    }
+
+    pub fn handle(&self, key: PoolKey) -> PoolHandle {
+        PoolHandle(Destination::Pool {
+            pool: self.clone(),
+            key: Box::new(key),
+        })
+    }
+}
+
+impl Default for ConnectionPool {
+    fn default() -> Self {
+        ConnectionPool::new()
+    }
+}
+
+impl fmt::Debug for ConnectionPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConnectionPool").finish()
+    }
+}
+
+/// Lets a response give its connection back to the pool once its body has been read.
+#[derive(Clone, Debug)]
+pub struct PoolHandle(Destination);
+
+#[derive(Clone, Debug)]
+enum Destination {
+    Pool { pool: ConnectionPool, key: Box<PoolKey> },
+    // The connection of a pipeline is handed from one response to the next, with the responses still buffered.
+    Pipeline(Arc<Mutex<Option<BufReader<BaseStream>>>>),
+}
+
+impl PoolHandle {
+    /// Create a handle which gives the connection back to a pipeline, keeping any data left in its buffer.
+    pub fn pipeline(slot: Arc<Mutex<Option<BufReader<BaseStream>>>>) -> PoolHandle {
+        PoolHandle(Destination::Pipeline(slot))
+    }
+
+    pub fn checkout(&self) -> Option<BufReader<BaseStream>> {
+        match &self.0 {
+            Destination::Pool { pool, key } => pool.checkout(key),
+            Destination::Pipeline(slot) => slot.lock().unwrap().take(),
+        }
+    }
+
+    pub fn release(self, reader: BufReader<BaseStream>) {
+        match self.0 {
+            Destination::Pool { pool, key } => pool.checkin(*key, reader),
+            Destination::Pipeline(slot) => *slot.lock().unwrap() = Some(reader),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Read};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use url::Url;
+
+    use super::{ConnectionPool, PoolKey};
+    use crate::request::BaseSettings;
+    use crate::streams::BaseStream;
+
+    fn key(url: &str) -> PoolKey {
+        PoolKey::new(&Url::parse(url).unwrap(), None, &BaseSettings::default()).unwrap()
+    }
+
+    #[test]
+    fn test_pool_key_default_port() {
+        assert_eq!(key("http://example.com/a"), key("http://example.com:80/b"));
+        assert_ne!(key("http://example.com/"), key("https://example.com/"));
+        assert_ne!(key("http://example.com/"), key("http://example.com:8080/"));
+    }
+
+    #[test]
+    fn test_pool_key_unix_socket() {
+        let url = Url::parse("http://localhost/").unwrap();
+        let settings = BaseSettings {
+            unix_socket: Some(PathBuf::from("/run/docker.sock")),
+            ..BaseSettings::default()
+        };
+        let socket = PoolKey::new(&url, None, &settings).unwrap();
+        assert_ne!(key("http://localhost/"), socket);
+    }
+
+    #[test]
+    fn test_pool_key_connection_settings() {
+        let url = Url::parse("https://localhost/").unwrap();
+        let with = |settings: BaseSettings| PoolKey::new(&url, None, &settings).unwrap();
+        let default = with(BaseSettings::default());
+
+        assert_ne!(
+            default,
+            with(BaseSettings {
+                accept_invalid_certs: true,
+                ..BaseSettings::default()
+            })
+        );
+        assert_ne!(
+            default,
+            with(BaseSettings {
+                local_address: Some([127, 0, 0, 1].into()),
+                ..BaseSettings::default()
+            })
+        );
+
+        let mut settings = BaseSettings::default();
+        settings
+            .resolve_overrides
+            .insert("localhost".into(), ([127, 0, 0, 1], 443).into());
+        assert_ne!(default, with(settings));
+
+        let mut settings = BaseSettings::default();
+        settings.certificates_changed();
+        assert_ne!(default, with(settings.clone()));
+        // Settings copied from a session share its certificates.
+        assert_eq!(with(settings.clone()), with(settings));
+    }
+
+    #[test]
+    fn test_pool_checkout() {
+        let pool = ConnectionPool::new();
+        pool.checkin(key("http://example.com/"), BufReader::new(BaseStream::mock(vec![])));
+
+        assert!(pool.checkout(&key("http://example.org/")).is_none());
+        assert!(pool.checkout(&key("http://example.com/")).is_some());
+        assert!(pool.checkout(&key("http://example.com/")).is_none());
+    }
+
+    #[test]
+    fn test_pool_idle_timeout() {
+        let pool = ConnectionPool::new();
+        pool.set_idle_timeout(Duration::from_secs(0));
+        pool.checkin(key("http://example.com/"), BufReader::new(BaseStream::mock(vec![])));
+
+        assert!(pool.checkout(&key("http://example.com/")).is_none());
+    }
+
+    #[test]
+    fn test_pool_idle_timeout_other_keys() {
+        let pool = ConnectionPool::new();
+        pool.set_idle_timeout(Duration::from_secs(0));
+        pool.checkin(key("http://example.com/"), BufReader::new(BaseStream::mock(vec![])));
+        pool.checkout(&key("http://example.org/"));
+
+        assert!(pool.state.lock().unwrap().idle.is_empty());
+    }
+
+    #[test]
+    fn test_pool_max_idle_per_host() {
+        let pool = ConnectionPool::new();
+        pool.set_max_idle_per_host(2);
+        for body in [b"1", b"2", b"3"] {
+            pool.checkin(
+                key("http://example.com/"),
+                BufReader::new(BaseStream::mock(body.to_vec())),
+            );
+        }
+        pool.checkin(key("http://example.org/"), BufReader::new(BaseStream::mock(vec![])));
+
+        // The oldest connection was closed, the most recently used one is reused first.
+        let mut body = String::new();
+        let mut conn = pool.checkout(&key("http://example.com/")).unwrap();
+        conn.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "3");
+        body.clear();
+        let mut conn = pool.checkout(&key("http://example.com/")).unwrap();
+        conn.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "2");
+        assert!(pool.checkout(&key("http://example.com/")).is_none());
+        assert!(pool.checkout(&key("http://example.org/")).is_some());
+    }
+
+    #[test]
+    fn test_pool_max_idle_per_host_lowered() {
+        let pool = ConnectionPool::new();
+        pool.checkin(key("http://example.com/"), BufReader::new(BaseStream::mock(vec![])));
+        pool.checkin(key("http://example.com/"), BufReader::new(BaseStream::mock(vec![])));
+        pool.set_max_idle_per_host(0);
+        assert!(pool.checkout(&key("http://example.com/")).is_none());
+
+        pool.checkin(key("http://example.com/"), BufReader::new(BaseStream::mock(vec![])));
+        assert!(pool.checkout(&key("http://example.com/")).is_none());
+    }
+
+    #[test]
+    fn test_pool_checkin_leftover_data() {
+        let pool = ConnectionPool::new();
+        let mut reader = BufReader::new(BaseStream::mock(b"garbage".to_vec()));
+        std::io::BufRead::fill_buf(&mut reader).unwrap();
+        pool.checkin(key("http://example.com/"), reader);
+
+        assert!(pool.checkout(&key("http://example.com/")).is_none());
+    }
+}
@@ -1,6 +1,8 @@
 use std::convert::TryInto;
 use std::fs;
-use std::io::{copy, Result as IoResult, Seek, SeekFrom, Write};
+use std::io::{self, copy, Read, Result as IoResult, Seek, SeekFrom, Write};
+
+use http::HeaderMap;
 
 /// The kinds of request bodies currently supported by this crate.
 #[derive(Debug, Clone, Copy)]
@@ -13,6 +15,45 @@ pub enum BodyKind {
     Chunked,
 }
 
+/// The encodings a request body can be compressed with, see `RequestBuilder::compress_body`.
+#[cfg(feature = "compress")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ContentEncoding {
+    /// The gzip format.
+    Gzip,
+    /// The zlib format, which is what the `deflate` content encoding stands for.
+    Deflate,
+}
+
+#[cfg(feature = "compress")]
+impl ContentEncoding {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+
+    /// Compress the body into a buffer.
+    pub(crate) fn compress<B: Body>(self, body: &mut B) -> IoResult<Vec<u8>> {
+        use flate2::write::{GzEncoder, ZlibEncoder};
+        use flate2::Compression;
+
+        match self {
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                body.write(&mut encoder)?;
+                encoder.finish()
+            }
+            ContentEncoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                body.write(&mut encoder)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
 /// A generic rewindable request body
 pub trait Body {
     /// Determine the kind of the request body
@@ -27,6 +68,14 @@ pub trait Body {
     fn content_type(&mut self) -> IoResult<Option<String>> {
         Ok(None)
     }
+
+    /// Check if the body can be written again, to send the request on a new connection when a reused one
+    /// turns out to be closed.
+    ///
+    /// This defaults to `true`, bodies which can only be written once must return `false`.
+    fn is_replayable(&self) -> bool {
+        true
+    }
 }
 
 /// An empty request body
@@ -90,11 +139,73 @@ impl Body for File {
     }
 }
 
+/// A request body streamed from any reader
+///
+/// If the length is known, the body is sent with a `Content-Length` header, otherwise it is sent
+/// using chunked encoding. The reader can only be consumed once, which means that this body cannot
+/// be sent again if a redirection requires it.
+#[derive(Debug)]
+pub struct Reader<R> {
+    reader: Option<R>,
+    len: Option<u64>,
+}
+
+impl<R: Read> Reader<R> {
+    /// Create a new body from the reader, which will produce `len` bytes if it is given.
+    pub fn new(reader: R, len: Option<u64>) -> Reader<R> {
+        Reader {
+            reader: Some(reader),
+            len,
+        }
+    }
+}
+
+impl<R: Read> Body for Reader<R> {
+    fn kind(&mut self) -> IoResult<BodyKind> {
+        Ok(match self.len {
+            Some(len) => BodyKind::KnownLength(len),
+            None => BodyKind::Chunked,
+        })
+    }
+
+    fn write<W: Write>(&mut self, mut writer: W) -> IoResult<()> {
+        let reader = self
+            .reader
+            .take()
+            .ok_or_else(|| io::Error::other("the body reader was already consumed"))?;
+
+        match self.len {
+            Some(len) => {
+                let copied = copy(&mut reader.take(len), &mut writer)?;
+                if copied < len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+            None => {
+                let mut reader = reader;
+                copy(&mut reader, &mut writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+}
+
 pub(crate) struct ChunkedWriter<W>(pub W);
 
 impl<W: Write> ChunkedWriter<W> {
-    pub fn close(mut self) -> IoResult<()> {
-        self.0.write_all(b"0\r\n\r\n")
+    /// Write the last chunk, followed by the trailers.
+    pub fn close(mut self, trailers: &HeaderMap) -> IoResult<()> {
+        self.0.write_all(b"0\r\n")?;
+        for (key, value) in trailers {
+            write!(self.0, "{}: ", key.as_str())?;
+            self.0.write_all(value.as_bytes())?;
+            write!(self.0, "\r\n")?;
+        }
+        self.0.write_all(b"\r\n")
     }
 }
 
@@ -140,3 +251,20 @@ mod json {
 
 #[cfg(feature = "json")]
 pub use json::Json;
+
+#[test]
+fn test_reader_known_length() {
+    let mut body = Reader::new(&b"hello world"[..], Some(5));
+    let mut buf = Vec::new();
+    assert!(matches!(body.kind().unwrap(), BodyKind::KnownLength(5)));
+    body.write(&mut buf).unwrap();
+    assert_eq!(buf, b"hello");
+    assert!(body.write(&mut buf).is_err());
+}
+
+#[test]
+fn test_reader_too_short() {
+    let mut body = Reader::new(&b"hello"[..], Some(10));
+    let err = body.write(Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,428 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use http::header::{HeaderMap, WWW_AUTHENTICATE};
+use http::Method;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// Credentials used to answer `Digest` challenges, as described in RFC 7616.
+///
+/// Clones share the count of requests sent with the last nonce received.
+#[derive(Clone)]
+pub struct DigestAuth {
+    username: String,
+    password: String,
+    last_nonce: Arc<Mutex<(String, u32)>>,
+}
+
+impl DigestAuth {
+    pub fn new(username: String, password: String) -> DigestAuth {
+        DigestAuth {
+            username,
+            password,
+            last_nonce: Arc::new(Mutex::new((String::new(), 0))),
+        }
+    }
+
+    /// Get the value of the `Authorization` header answering the `Digest` challenge of a `401 Unauthorized`
+    /// response to a request sent to `url`.
+    ///
+    /// Returns `None` if the response has no challenge this implementation can answer.
+    pub fn authorization(&self, headers: &HeaderMap, method: &Method, url: &Url) -> Option<String> {
+        let challenge = headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|val| val.to_str().ok())
+            .filter_map(Challenge::parse)
+            // SHA-256 is preferred when the server offers it along with MD5.
+            .max_by_key(|challenge| challenge.algorithm.hash == Hash::Sha256)?;
+
+        let uri = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_owned(),
+        };
+        let nc = {
+            let mut last_nonce = self.last_nonce.lock().unwrap();
+            if last_nonce.0 != challenge.nonce {
+                *last_nonce = (challenge.nonce.clone(), 0);
+            }
+            last_nonce.1 += 1;
+            last_nonce.1
+        };
+        Some(self.answer(&challenge, method, &uri, nc, &new_cnonce()))
+    }
+
+    fn answer(&self, challenge: &Challenge, method: &Method, uri: &str, nc: u32, cnonce: &str) -> String {
+        let algorithm = challenge.algorithm;
+        let h = |data: String| algorithm.hash.hex(data.as_bytes());
+
+        let mut ha1 = h(format!("{}:{}:{}", self.username, challenge.realm, self.password));
+        if algorithm.sess {
+            ha1 = h(format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+        }
+        let ha2 = h(format!("{}:{}", method.as_str(), uri));
+        let nc = format!("{:08x}", nc);
+
+        let response = if challenge.qop_auth {
+            h(format!("{}:{}:{}:{}:auth:{}", ha1, challenge.nonce, nc, cnonce, ha2))
+        } else {
+            h(format!("{}:{}:{}", ha1, challenge.nonce, ha2))
+        };
+
+        let mut header = format!(
+            "Digest username={}, realm={}, nonce={}, uri={}, algorithm={}, response=\"{}\"",
+            quote(&self.username),
+            quote(&challenge.realm),
+            quote(&challenge.nonce),
+            quote(uri),
+            algorithm.name(),
+            response,
+        );
+        if challenge.qop_auth {
+            let _ = write!(header, ", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce);
+        }
+        if let Some(opaque) = &challenge.opaque {
+            let _ = write!(header, ", opaque={}", quote(opaque));
+        }
+        header
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Hash {
+    Md5,
+    Sha256,
+}
+
+impl Hash {
+    fn hex(self, data: &[u8]) -> String {
+        let digest = match self {
+            Hash::Md5 => Md5::digest(data).to_vec(),
+            Hash::Sha256 => Sha256::digest(data).to_vec(),
+        };
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Algorithm {
+    hash: Hash,
+    sess: bool,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Algorithm> {
+        let (name, sess) = match name.len().checked_sub(5) {
+            Some(idx) if name.is_char_boundary(idx) && name[idx..].eq_ignore_ascii_case("-sess") => {
+                (&name[..idx], true)
+            }
+            _ => (name, false),
+        };
+        let hash = if name.eq_ignore_ascii_case("MD5") {
+            Hash::Md5
+        } else if name.eq_ignore_ascii_case("SHA-256") {
+            Hash::Sha256
+        } else {
+            return None;
+        };
+        Some(Algorithm { hash, sess })
+    }
+
+    fn name(self) -> &'static str {
+        match (self.hash, self.sess) {
+            (Hash::Md5, false) => "MD5",
+            (Hash::Md5, true) => "MD5-sess",
+            (Hash::Sha256, false) => "SHA-256",
+            (Hash::Sha256, true) => "SHA-256-sess",
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    qop_auth: bool,
+}
+
+impl Challenge {
+    /// Parse the value of a `WWW-Authenticate` header, keeping only the `Digest` challenges which can be answered.
+    fn parse(value: &str) -> Option<Challenge> {
+        let value = value.trim_start();
+        if value.len() < 7 || !value[..6].eq_ignore_ascii_case("digest") || !value[6..].starts_with(' ') {
+            return None;
+        }
+
+        let mut realm = None;
+        let mut nonce = None;
+        let mut opaque = None;
+        let mut algorithm = Some(Algorithm {
+            hash: Hash::Md5,
+            sess: false,
+        });
+        let mut qop = None;
+
+        for (key, val) in parse_params(&value[7..]) {
+            match key.to_ascii_lowercase().as_str() {
+                "realm" => realm = Some(val),
+                "nonce" => nonce = Some(val),
+                "opaque" => opaque = Some(val),
+                "algorithm" => algorithm = Algorithm::parse(&val),
+                "qop" => qop = Some(val),
+                _ => {}
+            }
+        }
+
+        // Servers which send a qop must be answered with one, only `auth` is supported.
+        let qop_auth = match qop {
+            Some(qop) if qop.split(',').any(|s| s.trim().eq_ignore_ascii_case("auth")) => true,
+            Some(_) => return None,
+            None => false,
+        };
+
+        Some(Challenge {
+            realm: realm?,
+            nonce: nonce?,
+            opaque,
+            algorithm: algorithm?,
+            qop_auth,
+        })
+    }
+}
+
+/// Parse comma separated `key=value` pairs, the values being tokens or quoted strings.
+fn parse_params(mut s: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+
+    loop {
+        s = s.trim_start_matches(|c: char| c == ',' || c.is_ascii_whitespace());
+        let eq = match s.find('=') {
+            Some(eq) => eq,
+            None => return params,
+        };
+        let key = s[..eq].trim().to_owned();
+        s = s[eq + 1..].trim_start();
+
+        let mut value = String::new();
+        if let Some(rest) = s.strip_prefix('"') {
+            let mut chars = rest.char_indices();
+            let mut end = rest.len();
+            while let Some((idx, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        end = idx + 1;
+                        break;
+                    }
+                    '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                    c => value.push(c),
+                }
+            }
+            s = &rest[end..];
+        } else {
+            let end = s.find(',').unwrap_or(s.len());
+            value.push_str(s[..end].trim());
+            s = &s[end..];
+        }
+
+        params.push((key, value));
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Create a client nonce which is unique within this process and hard to predict for the server.
+fn new_cnonce() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seed = format!(
+        "{}:{}:{}",
+        now.as_nanos(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    Hash::Md5.hex(seed.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::{HeaderMap, HeaderValue, WWW_AUTHENTICATE};
+    use http::Method;
+    use url::Url;
+
+    use super::{parse_params, Algorithm, Challenge, DigestAuth, Hash};
+
+    #[test]
+    fn test_md5() {
+        assert_eq!(Hash::Md5.hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(Hash::Md5.hex(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(Hash::Md5.hex(&[b'a'; 1000]), "cabe45dcc9ae5b66ba86600cca6b8ba8");
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            Hash::Sha256.hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            Hash::Sha256.hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            Hash::Sha256.hex(&[b'a'; 1000]),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_parse_params() {
+        assert_eq!(
+            parse_params(r#"realm="a \"b\", c", qop=auth,nonce="xyz""#),
+            vec![
+                ("realm".to_owned(), r#"a "b", c"#.to_owned()),
+                ("qop".to_owned(), "auth".to_owned()),
+                ("nonce".to_owned(), "xyz".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        let challenge = Challenge::parse(r#"Digest realm="test", qop="auth,auth-int", nonce="abc", opaque="xyz""#);
+        assert_eq!(
+            challenge,
+            Some(Challenge {
+                realm: "test".to_owned(),
+                nonce: "abc".to_owned(),
+                opaque: Some("xyz".to_owned()),
+                algorithm: Algorithm {
+                    hash: Hash::Md5,
+                    sess: false,
+                },
+                qop_auth: true,
+            })
+        );
+
+        let challenge = Challenge::parse(r#"digest realm="test", nonce="abc", algorithm=SHA-256-sess"#).unwrap();
+        assert_eq!(
+            challenge.algorithm,
+            Algorithm {
+                hash: Hash::Sha256,
+                sess: true,
+            }
+        );
+        assert!(!challenge.qop_auth);
+
+        assert_eq!(Challenge::parse(r#"Basic realm="test""#), None);
+        assert_eq!(
+            Challenge::parse(r#"Digest realm="test", nonce="abc", qop="auth-int""#),
+            None
+        );
+        assert_eq!(
+            Challenge::parse(r#"Digest realm="test", nonce="abc", algorithm=SHA-512"#),
+            None
+        );
+        assert_eq!(Challenge::parse(r#"Digest realm="test""#), None);
+    }
+
+    /// Example of RFC 2617, section 3.5.
+    #[test]
+    fn test_answer_rfc2617() {
+        let auth = DigestAuth::new("Mufasa".to_owned(), "Circle Of Life".to_owned());
+        let challenge = Challenge::parse(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            auth.answer(&challenge, &Method::GET, "/dir/index.html", 1, "0a4f113b"),
+            r#"Digest username="Mufasa", realm="testrealm@host.com", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", uri="/dir/index.html", algorithm=MD5, response="6629fae49393a05397450978507c4ef1", qop=auth, nc=00000001, cnonce="0a4f113b", opaque="5ccc069c403ebaf9f0171e9517f40e41""#
+        );
+    }
+
+    /// Examples of RFC 7616, section 3.9.1.
+    #[test]
+    fn test_answer_rfc7616() {
+        let auth = DigestAuth::new("Mufasa".to_owned(), "Circle of Life".to_owned());
+        let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+        let challenge = r#"realm="http-auth@example.org", qop="auth, auth-int", nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#;
+
+        let md5 = Challenge::parse(&format!("Digest {}, algorithm=MD5", challenge)).unwrap();
+        let header = auth.answer(&md5, &Method::GET, "/dir/index.html", 1, cnonce);
+        assert!(header.contains(r#"response="8ca523f5e9506fed4657c9700eebdbec""#));
+
+        let sha256 = Challenge::parse(&format!("Digest {}, algorithm=SHA-256", challenge)).unwrap();
+        let header = auth.answer(&sha256, &Method::GET, "/dir/index.html", 1, cnonce);
+        assert!(header.contains(r#"response="753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1""#));
+        assert!(header.contains("algorithm=SHA-256"));
+    }
+
+    #[test]
+    fn test_authorization_prefers_sha256() {
+        let mut headers = HeaderMap::new();
+        headers.append(WWW_AUTHENTICATE, HeaderValue::from_static(r#"Basic realm="test""#));
+        headers.append(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_static(r#"Digest realm="test", nonce="abc", algorithm=MD5"#),
+        );
+        headers.append(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_static(r#"Digest realm="test", nonce="abc", algorithm=SHA-256"#),
+        );
+
+        let auth = DigestAuth::new("user".to_owned(), "pass".to_owned());
+        let url = Url::parse("http://localhost/foo?a=b").unwrap();
+        let header = auth.authorization(&headers, &Method::GET, &url).unwrap();
+        assert!(header.contains("algorithm=SHA-256"));
+        assert!(header.contains(r#"uri="/foo?a=b""#));
+
+        assert!(auth.authorization(&HeaderMap::new(), &Method::GET, &url).is_none());
+    }
+
+    #[test]
+    fn test_authorization_nonce_count() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_static(r#"Digest realm="test", nonce="abc", qop=auth"#),
+        );
+
+        let auth = DigestAuth::new("user".to_owned(), "pass".to_owned());
+        let url = Url::parse("http://localhost/").unwrap();
+        assert!(auth
+            .authorization(&headers, &Method::GET, &url)
+            .unwrap()
+            .contains("nc=00000001"));
+        assert!(auth
+            .authorization(&headers, &Method::GET, &url)
+            .unwrap()
+            .contains("nc=00000002"));
+
+        headers.insert(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_static(r#"Digest realm="test", nonce="def", qop=auth"#),
+        );
+        assert!(auth
+            .authorization(&headers, &Method::GET, &url)
+            .unwrap()
+            .contains("nc=00000001"));
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+
+use http::HeaderMap;
+
+use crate::error::{ErrorKind, Result};
+use crate::parsing::{parse_response, Response};
+use crate::pool::{PoolHandle, PoolKey};
+use crate::streams::BaseStream;
+
+use super::body::{self, Body};
+use super::PreparedRequest;
+
+/// Sends several requests on one connection before reading their responses, which is HTTP/1.1 pipelining.
+///
+/// The connection is opened to the server of the first request, using the settings of that request. Every
+/// following request must be sent to the same server. The responses are read with `next_response`, in the order
+/// the requests were sent.
+///
+/// Only use a pipeline with servers known to support it. The connection is handed from a response to the next
+/// once its whole body was read, the body of a response must be read entirely before reading the next response
+/// or sending more requests. This only works for responses whose length is given by `Content-Length` or the
+/// chunked transfer encoding, requests must not be sent with `Connection: close`. A response which closes the
+/// connection ends the pipeline, reading the following ones fails with `ErrorKind::PipelineBroken`.
+///
+/// Requests are sent as they are: redirections are not followed, requests are not retried and the timeout of the
+/// whole request is not applied.
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    key: Option<PoolKey>,
+    connection: Arc<Mutex<Option<BufReader<BaseStream>>>>,
+    pending: VecDeque<PreparedRequest<body::Empty>>,
+}
+
+impl Pipeline {
+    /// Create an empty pipeline, which connects to the server when the first request is sent.
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Write a request on the connection of this pipeline, without waiting for its response.
+    pub fn send<B: Body>(&mut self, mut request: PreparedRequest<B>) -> Result {
+        let url = request.url.clone();
+        let method = request.method.clone();
+        let proxy = request.route(&url)?;
+        let key = PoolKey::new(&url, proxy.as_ref(), &request.base_settings)?;
+
+        let mut reader = match &self.key {
+            None => request.connect(&url, proxy.as_ref(), None)?,
+            Some(first) if *first != key => return Err(ErrorKind::PipelineOrigin.into()),
+            Some(_) => self
+                .connection
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or(ErrorKind::PipelineBroken)?,
+        };
+
+        // The connection is dropped if the request cannot be written, its state is unknown.
+        request.write_request(reader.get_mut(), &method, &url, proxy.as_ref(), true)?;
+        self.key = Some(key);
+        *self.connection.lock().unwrap() = Some(reader);
+
+        // Only what is needed to read the response is kept.
+        self.pending.push_back(PreparedRequest {
+            url,
+            method,
+            body: body::Empty,
+            trailers: HeaderMap::new(),
+            base_settings: request.base_settings,
+        });
+        Ok(())
+    }
+
+    /// Read the response of the oldest request whose response was not read yet.
+    ///
+    /// `None` is returned once the responses of all the requests sent were read.
+    pub fn next_response(&mut self) -> Option<Result<Response>> {
+        let request = self.pending.pop_front()?;
+        Some(self.read_response(&request))
+    }
+
+    fn read_response(&self, request: &PreparedRequest<body::Empty>) -> Result<Response> {
+        let reader = self
+            .connection
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(ErrorKind::PipelineBroken)?;
+        let handle = PoolHandle::pipeline(self.connection.clone());
+        let resp = parse_response(reader, request, &request.url, Some(handle))?;
+
+        #[cfg(feature = "cookies")]
+        if let Some(jar) = &request.base_settings.cookie_jar {
+            jar.store_response_cookies(&request.url, resp.headers());
+        }
+
+        Ok(resp)
+    }
+}
@@ -1,6 +1,7 @@
 use std::env;
+use std::net::IpAddr;
 
-use url::Url;
+use url::{Host, Url};
 use wildmatch::WildMatch;
 
 fn get_env(name: &str) -> Option<String> {
@@ -45,12 +46,107 @@ fn get_env_url(name: &str) -> Option<Url> {
     }
 }
 
+/// An entry of the list of hosts which bypass the proxy.
+#[derive(Clone, Debug)]
+enum NoProxy {
+    /// `*` matches every host.
+    All,
+    /// A domain name matches the domain itself and its subdomains.
+    Domain(String),
+    /// A pattern with `*` or `?` wildcards matches the whole hostname.
+    Pattern(WildMatch),
+    /// An IP network given in CIDR notation, a single address is a network with the longest prefix.
+    Network(IpAddr, u8),
+}
+
+impl NoProxy {
+    fn parse(entry: &str) -> Option<NoProxy> {
+        let entry = entry.trim().to_ascii_lowercase();
+        if entry.is_empty() {
+            return None;
+        }
+        if entry == "*" {
+            return Some(NoProxy::All);
+        }
+
+        if let Some((addr, prefix)) = entry.split_once('/') {
+            let network = parse_ip(addr).and_then(|addr| {
+                let prefix = prefix.parse::<u8>().ok().filter(|&prefix| prefix <= max_prefix(addr))?;
+                Some(NoProxy::Network(addr, prefix))
+            });
+            if network.is_none() {
+                warn!("Invalid network in the no proxy list: {}", entry);
+            }
+            return network;
+        }
+        if let Some(addr) = parse_ip(&entry) {
+            return Some(NoProxy::Network(addr, max_prefix(addr)));
+        }
+
+        if entry.contains(['*', '?']) {
+            Some(NoProxy::Pattern(WildMatch::new(&entry)))
+        } else {
+            Some(NoProxy::Domain(entry.trim_matches('.').to_owned()))
+        }
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let host = match (url.host(), url.host_str()) {
+            (Some(host), Some(host_str)) => (host, host_str),
+            _ => return false,
+        };
+
+        match (self, host) {
+            (NoProxy::All, _) => true,
+            (NoProxy::Domain(domain), (Host::Domain(host), _)) => {
+                let host = host.trim_end_matches('.');
+                host == domain || (host.ends_with(domain.as_str()) && host[..host.len() - domain.len()].ends_with('.'))
+            }
+            (NoProxy::Domain(_), _) => false,
+            (NoProxy::Pattern(pattern), (_, host_str)) => pattern.matches(host_str),
+            (NoProxy::Network(network, prefix), (Host::Ipv4(addr), _)) => {
+                in_network(IpAddr::V4(addr), *network, *prefix)
+            }
+            (NoProxy::Network(network, prefix), (Host::Ipv6(addr), _)) => {
+                in_network(IpAddr::V6(addr), *network, *prefix)
+            }
+            (NoProxy::Network(..), _) => false,
+        }
+    }
+}
+
+/// Parse an IP address, IPv6 addresses may be enclosed in brackets like in URLs.
+fn parse_ip(addr: &str) -> Option<IpAddr> {
+    addr.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+fn max_prefix(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn in_network(addr: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (addr, network) {
+        (IpAddr::V4(addr), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(addr) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(addr), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(addr) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
 /// Contains proxy settings and utilities to find which proxy to use for a given URL.
 #[derive(Clone, Debug)]
 pub struct ProxySettings {
     http_proxy: Option<Url>,
     https_proxy: Option<Url>,
-    no_proxy_patterns: Vec<WildMatch>,
+    no_proxy: Vec<NoProxy>,
 }
 
 impl ProxySettings {
@@ -62,38 +158,35 @@ impl ProxySettings {
     /// Get the proxy configuration from the environment using the `curl`/Unix proxy conventions.
     ///
     /// Only `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` are supported.
-    /// `NO_PROXY` supports wildcard patterns.
+    /// `NO_PROXY` is a comma separated list of entries, as described in
+    /// `ProxySettingsBuilder::add_no_proxy_pattern`.
     pub fn from_env() -> ProxySettings {
         let http_proxy = get_env_url("http_proxy");
         let https_proxy = get_env_url("https_proxy");
-        let no_proxy = get_env("no_proxy");
-
-        let no_proxy_patterns = no_proxy
-            .map(|x| x.split(',').map(|pat| WildMatch::new(pat.trim())).collect::<Vec<_>>())
+        let no_proxy = get_env("no_proxy")
+            .map(|x| x.split(',').filter_map(NoProxy::parse).collect::<Vec<_>>())
             .unwrap_or_default();
 
         ProxySettings {
             http_proxy,
             https_proxy,
-            no_proxy_patterns,
+            no_proxy,
         }
     }
 
     /// Get the proxy URL to use for the given URL.
     ///
-    /// None is returned if there is no proxy configured for the scheme or if the hostname
-    /// matches a pattern in the no proxy list.
+    /// None is returned if there is no proxy configured for the scheme or if the host
+    /// matches an entry of the no proxy list, the connection is then made directly.
     pub fn for_url(&self, url: &Url) -> Option<&Url> {
-        if let Some(host) = url.host_str() {
-            if !self.no_proxy_patterns.iter().any(|x| x.matches(host)) {
-                return match url.scheme() {
-                    "http" => self.http_proxy.as_ref(),
-                    "https" => self.https_proxy.as_ref(),
-                    _ => None,
-                };
-            }
+        if url.host().is_none() || self.no_proxy.iter().any(|x| x.matches(url)) {
+            return None;
+        }
+        match url.scheme() {
+            "http" => self.http_proxy.as_ref(),
+            "https" => self.https_proxy.as_ref(),
+            _ => None,
         }
-        None
     }
 }
 
@@ -110,7 +203,7 @@ impl ProxySettingsBuilder {
             inner: ProxySettings {
                 http_proxy: None,
                 https_proxy: None,
-                no_proxy_patterns: vec![],
+                no_proxy: vec![],
             },
         }
     }
@@ -133,12 +226,18 @@ impl ProxySettingsBuilder {
         self
     }
 
-    /// Add a hostname pattern to ignore when finding the proxy to use for a URL.
+    /// Add an entry to the list of hosts which are connected to directly instead of going through the proxy.
+    ///
+    /// The entry can be:
+    /// * `*`, which matches every host.
+    /// * A domain name like `mycompany.local` or `.mycompany.local`, which matches the domain and its subdomains.
+    /// * A pattern with wildcards like `*.mycompany.local`, which matches the whole hostname.
+    /// * An IP address like `10.1.2.3` or `::1`, or a network in CIDR notation like `10.0.0.0/8`, which match
+    ///   hosts given as IP addresses.
     ///
-    /// For instance `*.mycompany.local` will make every hostname which ends with `.mycompany.local`
-    /// not go trough the proxy.
+    /// Matching is case insensitive, empty and invalid entries are ignored.
     pub fn add_no_proxy_pattern(mut self, pattern: impl AsRef<str>) -> Self {
-        self.inner.no_proxy_patterns.push(WildMatch::new(pattern.as_ref()));
+        self.inner.no_proxy.extend(NoProxy::parse(pattern.as_ref()));
         self
     }
 
@@ -159,7 +258,7 @@ fn test_proxy_for_url() {
     let s = ProxySettings {
         http_proxy: Some("http://proxy1:3128".parse().unwrap()),
         https_proxy: Some("http://proxy2:3128".parse().unwrap()),
-        no_proxy_patterns: vec![WildMatch::new("*.com")],
+        no_proxy: vec![NoProxy::Pattern(WildMatch::new("*.com"))],
     };
 
     assert_eq!(
@@ -174,3 +273,71 @@ fn test_proxy_for_url() {
 
     assert_eq!(s.for_url(&Url::parse("https://reddit.com").unwrap()), None);
 }
+
+#[cfg(test)]
+fn no_proxy_matches(entry: &str, url: &str) -> bool {
+    NoProxy::parse(entry).unwrap().matches(&Url::parse(url).unwrap())
+}
+
+#[test]
+fn test_no_proxy_domain() {
+    assert!(no_proxy_matches("example.com", "http://example.com"));
+    assert!(no_proxy_matches("example.com", "http://www.Example.com:8080/foo"));
+    assert!(no_proxy_matches(".example.com", "http://example.com"));
+    assert!(no_proxy_matches(".example.com", "https://a.b.example.com"));
+    assert!(no_proxy_matches("Example.COM", "http://example.com."));
+    assert!(!no_proxy_matches("example.com", "http://notexample.com"));
+    assert!(!no_proxy_matches("example.com", "http://example.com.evil.org"));
+    assert!(!no_proxy_matches(".example.com", "http://example.org"));
+}
+
+#[test]
+fn test_no_proxy_wildcards() {
+    assert!(no_proxy_matches("*", "http://example.com"));
+    assert!(no_proxy_matches("*", "http://10.0.0.1"));
+    assert!(no_proxy_matches("*.local", "http://printer.local"));
+    assert!(!no_proxy_matches("*.local", "http://printer.localhost"));
+    assert!(no_proxy_matches("192.168.*", "http://192.168.1.1"));
+}
+
+#[test]
+fn test_no_proxy_cidr() {
+    assert!(no_proxy_matches("10.0.0.0/8", "http://10.20.30.40"));
+    assert!(!no_proxy_matches("10.0.0.0/8", "http://11.0.0.1"));
+    assert!(no_proxy_matches("192.168.1.0/24", "http://192.168.1.255"));
+    assert!(!no_proxy_matches("192.168.1.0/24", "http://192.168.2.1"));
+    assert!(no_proxy_matches("0.0.0.0/0", "http://8.8.8.8"));
+    assert!(!no_proxy_matches("10.0.0.0/8", "http://[::1]"));
+    assert!(no_proxy_matches("fd00::/8", "http://[fd12:3456::1]"));
+    assert!(!no_proxy_matches("fd00::/8", "http://[fe80::1]"));
+    assert!(!no_proxy_matches("10.0.0.0/8", "http://10.example.com"));
+}
+
+#[test]
+fn test_no_proxy_ip() {
+    assert!(no_proxy_matches("127.0.0.1", "http://127.0.0.1:8080"));
+    assert!(!no_proxy_matches("127.0.0.1", "http://127.0.0.2"));
+    assert!(no_proxy_matches("::1", "http://[::1]"));
+    assert!(no_proxy_matches("[::1]", "http://[::1]"));
+}
+
+#[test]
+fn test_no_proxy_invalid() {
+    assert!(NoProxy::parse("").is_none());
+    assert!(NoProxy::parse("  ").is_none());
+    assert!(NoProxy::parse("10.0.0.0/33").is_none());
+    assert!(NoProxy::parse("example.com/8").is_none());
+}
+
+#[test]
+fn test_proxy_bypassed() {
+    let s = ProxySettings::builder()
+        .http_proxy(Url::parse("http://proxy:3128").unwrap())
+        .add_no_proxy_pattern(".internal.corp")
+        .add_no_proxy_pattern("10.0.0.0/8")
+        .build();
+
+    assert!(s.for_url(&Url::parse("http://wiki.internal.corp").unwrap()).is_none());
+    assert!(s.for_url(&Url::parse("http://10.1.1.1").unwrap()).is_none());
+    assert!(s.for_url(&Url::parse("http://example.com").unwrap()).is_some());
+}
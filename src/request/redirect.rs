@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use http::{Method, StatusCode};
+use url::Url;
+
+/// What to do with a redirection, as decided by the policy given to `redirect_policy`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RedirectAction {
+    /// Follow the redirection.
+    Follow,
+    /// Stop and return the redirection response.
+    Stop,
+    /// Stop and return an `ErrorKind::RedirectRefused` error.
+    Error,
+}
+
+pub(crate) type RedirectPolicy = Arc<dyn Fn(&Url, StatusCode) -> RedirectAction + Send + Sync>;
+
+/// Describes how the method of a request is changed when a redirection is followed.
+///
+/// Whatever the policy, a request whose method is changed to `GET` is sent again without its body.
+/// `307 Temporary Redirect` and `308 Permanent Redirect` never change the method.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RedirectMethodPolicy {
+    /// Rewrite methods the way browsers do.
+    ///
+    /// `303 See Other` changes any method except `HEAD` to `GET`, while `301 Moved Permanently`
+    /// and `302 Found` change `POST` to `GET`. This is the default.
+    #[default]
+    Browser,
+    /// Rewrite methods only when the specification requires it.
+    ///
+    /// `303 See Other` changes any method except `HEAD` to `GET`, every other redirection keeps
+    /// the original method and body.
+    Strict,
+}
+
+impl RedirectMethodPolicy {
+    /// Get the method that must be used to follow a redirection with the given status.
+    pub(crate) fn redirect_method(self, status: StatusCode, method: &Method) -> Method {
+        match status {
+            StatusCode::SEE_OTHER if method != Method::HEAD => Method::GET,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND
+                if self == RedirectMethodPolicy::Browser && method == Method::POST =>
+            {
+                Method::GET
+            }
+            _ => method.clone(),
+        }
+    }
+}
+
+#[test]
+fn test_redirect_method_see_other() {
+    for &policy in &[RedirectMethodPolicy::Browser, RedirectMethodPolicy::Strict] {
+        assert_eq!(
+            policy.redirect_method(StatusCode::SEE_OTHER, &Method::POST),
+            Method::GET
+        );
+        assert_eq!(policy.redirect_method(StatusCode::SEE_OTHER, &Method::PUT), Method::GET);
+        assert_eq!(
+            policy.redirect_method(StatusCode::SEE_OTHER, &Method::HEAD),
+            Method::HEAD
+        );
+    }
+}
+
+#[test]
+fn test_redirect_method_browser() {
+    let policy = RedirectMethodPolicy::Browser;
+    assert_eq!(policy.redirect_method(StatusCode::FOUND, &Method::POST), Method::GET);
+    assert_eq!(
+        policy.redirect_method(StatusCode::MOVED_PERMANENTLY, &Method::POST),
+        Method::GET
+    );
+    assert_eq!(policy.redirect_method(StatusCode::FOUND, &Method::PUT), Method::PUT);
+    assert_eq!(
+        policy.redirect_method(StatusCode::TEMPORARY_REDIRECT, &Method::POST),
+        Method::POST
+    );
+    assert_eq!(
+        policy.redirect_method(StatusCode::PERMANENT_REDIRECT, &Method::POST),
+        Method::POST
+    );
+}
+
+#[test]
+fn test_redirect_method_strict() {
+    let policy = RedirectMethodPolicy::Strict;
+    assert_eq!(policy.redirect_method(StatusCode::FOUND, &Method::POST), Method::POST);
+    assert_eq!(
+        policy.redirect_method(StatusCode::MOVED_PERMANENTLY, &Method::POST),
+        Method::POST
+    );
+}
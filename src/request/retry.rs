@@ -0,0 +1,174 @@
+use std::io;
+use std::time::Duration;
+
+use http::header::{HeaderMap, RETRY_AFTER};
+use http::StatusCode;
+
+use crate::error::{Error, ErrorKind};
+
+/// Describes when and how a request that failed is sent again.
+///
+/// Every attempt sends the whole request again, following redirections if needed. The delay between two
+/// attempts starts at the initial backoff and doubles after every attempt, up to the maximum backoff. When a
+/// response with a retried status has a `Retry-After` header given in seconds, that delay is used instead, up to
+/// the maximum backoff.
+///
+/// The body of the request is written again on every attempt. Requests with bodies which can only be written
+/// once, like `body::Reader`, are never retried.
+///
+/// The default policy never retries requests.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    connection_errors: bool,
+    timeouts: bool,
+    statuses: Vec<StatusCode>,
+}
+
+impl RetryPolicy {
+    /// Create a policy which retries a request up to `max_retries` times after the first attempt.
+    ///
+    /// Refused, reset and aborted connections as well as timeouts are retried. No status code is retried
+    /// unless added with `retry_status`. The backoff starts at 100ms and is limited to 10s.
+    pub fn new(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Sets the delay before the first retry and the maximum delay between two attempts.
+    pub fn backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> RetryPolicy {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets if refused, reset and aborted connections are retried.
+    pub fn retry_connection_errors(mut self, retry: bool) -> RetryPolicy {
+        self.connection_errors = retry;
+        self
+    }
+
+    /// Sets if requests which timed out are retried.
+    pub fn retry_timeouts(mut self, retry: bool) -> RetryPolicy {
+        self.timeouts = retry;
+        self
+    }
+
+    /// Adds a status code for which the request is retried, such as `503 Service Unavailable`.
+    ///
+    /// The last response is returned when no retry is left.
+    pub fn retry_status(mut self, status: StatusCode) -> RetryPolicy {
+        self.statuses.push(status);
+        self
+    }
+
+    pub(crate) fn retries_error(&self, attempt: u32, err: &Error) -> bool {
+        attempt < self.max_retries
+            && match err.kind() {
+                ErrorKind::Io(err) => {
+                    matches!(
+                        err.kind(),
+                        io::ErrorKind::ConnectionRefused
+                            | io::ErrorKind::ConnectionReset
+                            | io::ErrorKind::ConnectionAborted
+                    ) && self.connection_errors
+                }
+                ErrorKind::Timeout => self.timeouts,
+                _ => false,
+            }
+    }
+
+    pub(crate) fn retries_status(&self, attempt: u32, status: StatusCode) -> bool {
+        attempt < self.max_retries && self.statuses.contains(&status)
+    }
+
+    /// Get the delay before sending the request again, after the given number of attempts failed.
+    pub(crate) fn delay(&self, attempt: u32, headers: Option<&HeaderMap>) -> Duration {
+        let retry_after = headers
+            .and_then(|headers| headers.get(RETRY_AFTER))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+        if let Some(secs) = retry_after {
+            return Duration::from_secs(secs).min(self.max_backoff);
+        }
+
+        self.initial_backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            connection_errors: true,
+            timeouts: true,
+            statuses: Vec::new(),
+        }
+    }
+}
+
+#[test]
+fn test_retry_policy_default() {
+    let policy = RetryPolicy::default();
+    let err = Error::from(io::Error::from(io::ErrorKind::ConnectionRefused));
+    assert!(!policy.retries_error(0, &err));
+    assert!(!policy.retries_status(0, StatusCode::SERVICE_UNAVAILABLE));
+}
+
+#[test]
+fn test_retry_policy_errors() {
+    let policy = RetryPolicy::new(2);
+    let refused = Error::from(io::Error::from(io::ErrorKind::ConnectionRefused));
+    let timed_out = Error::from(ErrorKind::Timeout);
+    let other = Error::from(io::Error::from(io::ErrorKind::InvalidData));
+
+    assert!(policy.retries_error(0, &refused));
+    assert!(policy.retries_error(1, &timed_out));
+    assert!(!policy.retries_error(2, &refused));
+    assert!(!policy.retries_error(0, &other));
+    assert!(!policy.retries_error(0, &ErrorKind::TooManyRedirections.into()));
+
+    let policy = RetryPolicy::new(2).retry_connection_errors(false).retry_timeouts(false);
+    assert!(!policy.retries_error(0, &refused));
+    assert!(!policy.retries_error(0, &timed_out));
+}
+
+#[test]
+fn test_retry_policy_status() {
+    let policy = RetryPolicy::new(1).retry_status(StatusCode::SERVICE_UNAVAILABLE);
+    assert!(policy.retries_status(0, StatusCode::SERVICE_UNAVAILABLE));
+    assert!(!policy.retries_status(1, StatusCode::SERVICE_UNAVAILABLE));
+    assert!(!policy.retries_status(0, StatusCode::BAD_GATEWAY));
+}
+
+#[test]
+fn test_retry_policy_delay() {
+    let policy = RetryPolicy::new(10).backoff(Duration::from_millis(100), Duration::from_secs(1));
+    assert_eq!(policy.delay(0, None), Duration::from_millis(100));
+    assert_eq!(policy.delay(1, None), Duration::from_millis(200));
+    assert_eq!(policy.delay(3, None), Duration::from_millis(800));
+    assert_eq!(policy.delay(4, None), Duration::from_secs(1));
+    assert_eq!(policy.delay(100, None), Duration::from_secs(1));
+}
+
+#[test]
+fn test_retry_policy_retry_after() {
+    let policy = RetryPolicy::new(1);
+    let mut headers = HeaderMap::new();
+    headers.insert(RETRY_AFTER, "3".parse().unwrap());
+    assert_eq!(policy.delay(0, Some(&headers)), Duration::from_secs(3));
+
+    headers.insert(RETRY_AFTER, "86400".parse().unwrap());
+    assert_eq!(policy.delay(0, Some(&headers)), Duration::from_secs(10));
+
+    headers.insert(RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+    assert_eq!(policy.delay(0, Some(&headers)), Duration::from_millis(100));
+}
@@ -1,19 +1,39 @@
 use std::convert::TryInto;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::time::Duration;
 
-use http::header::{HeaderValue, IntoHeaderName};
-use http::Method;
+use http::header::{AsHeaderName, HeaderMap, HeaderValue, IntoHeaderName, USER_AGENT};
+use http::{Method, StatusCode, Version};
+use url::Url;
 
 #[cfg(feature = "charsets")]
 use crate::charsets::Charset;
+#[cfg(feature = "cookies")]
+use crate::cookies::CookieJar;
 use crate::error::{Error, Result};
+use crate::pool::ConnectionPool;
 use crate::request::proxy::ProxySettings;
-use crate::request::{header_append, header_insert, BaseSettings, RequestBuilder};
-use crate::tls::Certificate;
+use crate::request::{
+    header_append, header_insert, BaseSettings, RedirectAction, RedirectMethodPolicy, RequestBuilder, RetryPolicy,
+};
+use crate::skip_debug::SkipDebug;
+use crate::tls::{self, Certificate, TlsVersion};
 
 /// `Session` is a type that can carry settings over multiple requests. The settings applied to the
 /// `Session` are applied to every request created from this `Session`.
-#[derive(Debug, Default)]
+///
+/// The headers of the `Session`, such as an API key, are the default headers of its requests. A request
+/// created from the `Session` starts with a copy of them: `RequestBuilder::header` replaces the value given
+/// by the `Session`, `RequestBuilder::header_append` adds a value next to it and `RequestBuilder::remove_header`
+/// removes it. Changing the `Session` afterwards does not change the requests already created.
+///
+/// Requests created from a `Session` keep their connection alive and reuse it for the next request
+/// sent to the same host, once the body of the `Response` has been read to the end.
+///
+/// Cloning a `Session` copies its settings, the clones share the same connection pool and cookie jar.
+#[derive(Clone, Debug)]
 pub struct Session {
     base_settings: BaseSettings,
 }
@@ -21,9 +41,30 @@ pub struct Session {
 impl Session {
     /// Create a new `Session` with default settings.
     pub fn new() -> Session {
-        Session {
-            base_settings: BaseSettings::default(),
-        }
+        let mut base_settings = BaseSettings::default();
+        base_settings.pool = Some(ConnectionPool::with_settings(&base_settings));
+        Session { base_settings }
+    }
+
+    /// Create a new `RequestBuilder` with the given method and this Session's settings applied on it.
+    ///
+    /// # Panics
+    /// This method will panic if the URL is invalid or if the method is `CONNECT`.
+    pub fn request<U>(&self, method: Method, base_url: U) -> RequestBuilder
+    where
+        U: AsRef<str>,
+    {
+        RequestBuilder::with_settings(method, base_url, self.base_settings.clone())
+    }
+
+    /// Try to create a new `RequestBuilder` with the given method and this Session's settings applied on it.
+    ///
+    /// If the URL is invalid or if the method is `CONNECT`, an error is returned.
+    pub fn try_request<U>(&self, method: Method, base_url: U) -> Result<RequestBuilder>
+    where
+        U: AsRef<str>,
+    {
+        RequestBuilder::try_with_settings(method, base_url, self.base_settings.clone())
     }
 
     /// Create a new `RequestBuilder` with the GET method and this Session's settings applied on it.
@@ -129,6 +170,10 @@ impl Session {
     ///
     /// If the header is already present, the value will be replaced. If you wish to append a new header,
     /// use `header_append`.
+    ///
+    /// Values containing control characters, such as line breaks, are refused with
+    /// `ErrorKind::InvalidHeaderValue`, which gives the name of the header. Bytes above 0x7F, the `obs-text` of
+    /// RFC 7230, are allowed and sent as is.
     pub fn try_header<H, V>(&mut self, header: H, value: V) -> Result<()>
     where
         H: IntoHeaderName,
@@ -142,6 +187,8 @@ impl Session {
     /// Append a new header to this `Request`.
     ///
     /// The new header is always appended to the `Request`, even if the header already exists.
+    ///
+    /// Invalid values are refused like in `try_header`.
     pub fn try_header_append<H, V>(&mut self, header: H, value: V) -> Result<()>
     where
         H: IntoHeaderName,
@@ -152,6 +199,33 @@ impl Session {
         Ok(())
     }
 
+    /// Set the `User-Agent` header of this `Session`.
+    ///
+    /// It replaces the default `User-Agent`, `attohttpc/<version>`.
+    ///
+    /// # Panics
+    /// This method will panic if the value is invalid.
+    pub fn user_agent<V>(&mut self, user_agent: V)
+    where
+        V: TryInto<HeaderValue>,
+        Error: From<V::Error>,
+    {
+        self.header(USER_AGENT, user_agent);
+    }
+
+    /// Remove a header from this `Session`, including all of its values.
+    pub fn remove_header<H>(&mut self, header: H)
+    where
+        H: AsHeaderName,
+    {
+        self.base_settings.headers.remove(header);
+    }
+
+    /// Replace all the headers of this `Session` with the given map.
+    pub fn headers(&mut self, headers: HeaderMap) {
+        self.base_settings.headers = headers;
+    }
+
     /// Set the maximum number of headers accepted in responses to this request.
     ///
     /// The default is 100.
@@ -159,6 +233,58 @@ impl Session {
         self.base_settings.max_headers = max_headers;
     }
 
+    /// Set the maximum size in bytes of the head of responses to this request, its status line and headers.
+    ///
+    /// The default is 64 KiB.
+    pub fn max_header_size(&mut self, max_header_size: usize) {
+        self.base_settings.max_header_size = max_header_size;
+    }
+
+    /// Sets if the responses of this `Session` are parsed leniently, to accept servers which do not follow the specification.
+    ///
+    /// When enabled, the following is accepted:
+    /// * a header line starting with a space or a tab, the obsolete line folding of RFC 7230, which is joined to
+    ///   the value of the previous header with a space. Such a response is refused with
+    ///   `InvalidResponseKind::FoldedHeader` otherwise.
+    /// * header lines ending with a bare LF instead of CRLF. A line without a colon continues the previous header.
+    /// * an HTTP version in lowercase, such as `http/1.1`, which is treated like HTTP/1.0 otherwise.
+    ///
+    /// A missing or empty reason phrase is always accepted, see `Response::reason`.
+    ///
+    /// This value defaults to false.
+    pub fn lenient(&mut self, lenient: bool) {
+        self.base_settings.lenient = lenient;
+    }
+
+    /// Sets if the exact bytes of the status line and headers of responses are kept, see `Response::raw_head`.
+    ///
+    /// This helps to debug the casing and order of headers, which are lost in the `HeaderMap`.
+    ///
+    /// This value defaults to false.
+    pub fn capture_raw(&mut self, capture_raw: bool) {
+        self.base_settings.capture_raw = capture_raw;
+    }
+
+    /// Set the maximum size in bytes of the body of responses to this request.
+    ///
+    /// The response is refused if its `Content-Length` is larger, and reading its body fails with
+    /// `ErrorKind::BodyTooLarge` once more bytes are received. The limit applies to the decompressed body.
+    ///
+    /// The default is no limit.
+    pub fn max_body_size(&mut self, max_body_size: u64) {
+        self.base_settings.max_body_size = Some(max_body_size);
+    }
+
+    /// Limit the rate at which the bodies of the requests are written and the bodies of their responses are read, in bytes per second.
+    ///
+    /// This is a best-effort token bucket which sleeps once the rate is exceeded, the rate is only respected on
+    /// average. Bodies are cut into bursts of a tenth of the rate, headers are not throttled.
+    ///
+    /// The default is no limit.
+    pub fn max_bandwidth(&mut self, bytes_per_sec: u64) {
+        self.base_settings.max_bandwidth = Some(bytes_per_sec);
+    }
+
     /// Set the maximum number of redirections this `Request` can perform.
     ///
     /// The default is 5.
@@ -168,13 +294,124 @@ impl Session {
 
     /// Sets if this `Request` should follow redirects, 3xx codes.
     ///
-    /// This value defaults to true.
+    /// This is a shorthand which replaces the policy set by `redirect_policy` with one that follows every
+    /// redirection, or none of them. This value defaults to true.
     pub fn follow_redirects(&mut self, follow_redirects: bool) {
         self.base_settings.follow_redirects = follow_redirects;
+        self.base_settings.redirect_policy = SkipDebug(None);
+    }
+
+    /// Sets a policy which decides if each redirection of the requests of this `Session` is followed.
+    ///
+    /// The policy is called with the URL of the redirection target and the status of the redirection
+    /// response before it is followed. The maximum number of redirections still applies.
+    pub fn redirect_policy<F>(&mut self, policy: F)
+    where
+        F: Fn(&Url, StatusCode) -> RedirectAction + Send + Sync + 'static,
+    {
+        self.base_settings.follow_redirects = true;
+        self.base_settings.redirect_policy = SkipDebug(Some(Arc::new(policy)));
+    }
+
+    /// Sets if redirections from `https` to `http` are followed.
+    ///
+    /// Such a redirection would send the following requests in plain text, they fail with
+    /// `ErrorKind::InsecureRedirect` unless this is enabled. Redirections from `http` to `https` are always
+    /// allowed.
+    ///
+    /// This value defaults to false.
+    pub fn allow_downgrade(&mut self, allow_downgrade: bool) {
+        self.base_settings.allow_downgrade = allow_downgrade;
+    }
+
+    /// Sets if the redirections followed by this `Request` are recorded in the history of the response.
+    ///
+    /// This value defaults to false.
+    pub fn record_history(&mut self, record_history: bool) {
+        self.base_settings.record_history = record_history;
+    }
+
+    /// Sets how the method of this `Request` is changed when following redirects.
+    ///
+    /// This value defaults to `RedirectMethodPolicy::Browser`.
+    pub fn redirect_method_policy(&mut self, policy: RedirectMethodPolicy) {
+        self.base_settings.redirect_method_policy = policy;
+    }
+
+    /// Sets when and how the requests of this `Session` are sent again if it fails.
+    ///
+    /// This value defaults to `RetryPolicy::default()`, which never retries requests.
+    pub fn retry_policy(&mut self, policy: RetryPolicy) {
+        self.base_settings.retry_policy = policy;
+    }
+
+    /// Connects to `addr` instead of the addresses `host` resolves to.
+    ///
+    /// The port of the URL is ignored and the port of `addr` is used instead. The `Host` header and the name
+    /// used to verify TLS certificates are still taken from the URL, see `sni_hostname` to change the latter.
+    /// When a proxy is used, this applies to the host name of the proxy.
+    pub fn resolve<H>(&mut self, host: H, addr: SocketAddr)
+    where
+        H: AsRef<str>,
+    {
+        self.base_settings
+            .resolve_overrides
+            .insert(host.as_ref().to_ascii_lowercase(), addr);
+    }
+
+    /// Sets the name sent in the TLS handshake and used to verify the certificate of the server, instead of the
+    /// host of the URL.
+    ///
+    /// This is useful when connecting to an IP address, for instance with `resolve`. When an HTTPS proxy is used,
+    /// the name of the proxy is not affected. This value defaults to `None`.
+    pub fn sni_hostname<H>(&mut self, name: H)
+    where
+        H: AsRef<str>,
+    {
+        self.base_settings.sni_hostname = Some(name.as_ref().to_owned());
+    }
+
+    /// Sets the local address the connections are bound to.
+    ///
+    /// Only the addresses of the host with the same family as `addr` are tried. This value defaults to `None`,
+    /// letting the operating system choose the address.
+    pub fn local_address(&mut self, addr: IpAddr) {
+        self.base_settings.local_address = Some(addr);
+    }
+
+    /// Sends the requests of this `Session` over the Unix domain socket at `path` instead of a TCP connection.
+    ///
+    /// The host of the URL is only used for the `Host` header and proxies are not used. Only `http` URLs can be
+    /// sent over a Unix socket. This value defaults to `None`.
+    #[cfg(unix)]
+    pub fn unix_socket<P>(&mut self, path: P)
+    where
+        P: Into<std::path::PathBuf>,
+    {
+        self.base_settings.unix_socket = Some(path.into());
+    }
+
+    /// Sets if `TCP_NODELAY` is set on the connections, disabling Nagle's algorithm.
+    ///
+    /// The option is set on the TCP socket, also when TLS is used. This value defaults to true.
+    pub fn tcp_nodelay(&mut self, tcp_nodelay: bool) {
+        self.base_settings.tcp_nodelay = tcp_nodelay;
+    }
+
+    /// Sets the HTTP version of the requests of this `Session`.
+    ///
+    /// Only HTTP/1.0 and HTTP/1.1 are supported, preparing a request fails with other versions. HTTP/1.0
+    /// connections are never reused and bodies without a known length cannot be sent with HTTP/1.0.
+    ///
+    /// This value defaults to HTTP/1.1.
+    pub fn version(&mut self, version: Version) {
+        self.base_settings.version = version;
     }
 
     /// Sets a connect timeout for this request.
     ///
+    /// The request fails with `ErrorKind::Timeout` when no connection could be made in time.
+    ///
     /// The default is 30 seconds.
     pub fn connect_timeout(&mut self, duration: Duration) {
         self.base_settings.connect_timeout = duration;
@@ -182,18 +419,42 @@ impl Session {
 
     /// Sets a read timeout for this request.
     ///
+    /// It applies to every read operation on the socket, use `timeout` to limit the duration of the
+    /// whole request. A read which waits longer makes the request fail with `ErrorKind::Timeout`.
+    ///
     /// The default is 30 seconds.
     pub fn read_timeout(&mut self, duration: Duration) {
         self.base_settings.read_timeout = duration;
     }
 
+    /// Sets a write timeout for this request.
+    ///
+    /// It applies to every write operation on the socket, use `timeout` to limit the duration of the
+    /// whole request. A server which stops reading the request, for instance during a large upload,
+    /// makes the request fail with `ErrorKind::Timeout` once this timeout is reached.
+    ///
+    /// The default is 30 seconds.
+    pub fn write_timeout(&mut self, duration: Duration) {
+        self.base_settings.write_timeout = duration;
+    }
+
     /// Sets a timeout for the whole request.
     ///
-    /// Applies after a TCP connection is established. Defaults to no timeout.
+    /// Covers the connection attempts, the redirections that are followed and the reading of the response.
+    /// The request fails with `ErrorKind::Timeout` once it is reached. Defaults to no timeout.
     pub fn timeout(&mut self, duration: Duration) {
         self.base_settings.timeout = Some(duration);
     }
 
+    /// Sets a token to cancel the requests of this `Session` from another thread by setting it to true.
+    ///
+    /// The token is checked before connecting, before following a redirection and between the reads of the
+    /// body, every request fails with `ErrorKind::Cancelled` once it is set. A blocked read is not interrupted, see
+    /// `read_timeout` to limit how long it can take. Defaults to no token.
+    pub fn cancel_token(&mut self, token: Arc<AtomicBool>) {
+        self.base_settings.cancel_token = Some(token);
+    }
+
     /// Sets the proxy settigns for this request.
     ///
     /// If left untouched, the defaults are to use system proxy settings found in environment variables.
@@ -203,8 +464,8 @@ impl Session {
 
     /// Set the default charset to use while parsing the response of this `Request`.
     ///
-    /// If the response does not say which charset it uses, this charset will be used to decode the request.
-    /// This value defaults to `None`, in which case ISO-8859-1 is used.
+    /// If the `Content-Type` of the response has no `charset` parameter, or an unknown one, this charset will be
+    /// used to decode the request. This value defaults to `None`, in which case ISO-8859-1 is used.
     #[cfg(feature = "charsets")]
     pub fn default_charset(&mut self, default_charset: Option<Charset>) {
         self.base_settings.default_charset = default_charset;
@@ -213,12 +474,24 @@ impl Session {
     /// Sets if this `Request` will announce that it accepts compression.
     ///
     /// This value defaults to true. Note that this only lets the browser know that this `Request` supports
-    /// compression, the server might choose not to compress the content.
+    /// compression, the server might choose not to compress the content. An `Accept-Encoding` header
+    /// set on this `Session` is never replaced.
     #[cfg(feature = "compress")]
     pub fn allow_compression(&mut self, allow_compression: bool) {
         self.base_settings.allow_compression = allow_compression;
     }
 
+    /// Sets the `CookieJar` shared by the requests of this `Session`.
+    ///
+    /// Cookies set by a response are sent with the following requests that match them.
+    /// This value defaults to `None`.
+    ///
+    /// This method only exists when the `cookies` feature is enabled.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_jar(&mut self, cookie_jar: CookieJar) {
+        self.base_settings.cookie_jar = Some(cookie_jar);
+    }
+
     /// Sets if this `Request` will accept invalid TLS certificates.
     ///
     /// Accepting invalid certificates implies that invalid hostnames are accepted
@@ -245,8 +518,146 @@ impl Session {
         self.base_settings.accept_invalid_hostnames = accept_invalid_hostnames;
     }
 
+    /// Sets the oldest version of the TLS protocol accepted when connecting to a server.
+    ///
+    /// The connection fails during the TLS handshake if the server only supports older versions. The `tls-rustls`
+    /// feature never uses versions older than TLS 1.2.
+    ///
+    /// The default value is `TlsVersion::Tls12`.
+    pub fn min_tls_version(&mut self, version: TlsVersion) {
+        self.base_settings.min_tls_version = version;
+    }
+
     /// Adds a root certificate that will be trusted.
     pub fn add_root_certificate(&mut self, cert: Certificate) {
         self.base_settings.root_certificates.0.push(cert);
+        self.base_settings.certificates_changed();
     }
+
+    /// Sets the client certificate presented to servers which require one during the TLS handshake.
+    ///
+    /// `cert_pem` contains a PEM encoded certificate chain and `key_pem` the PEM encoded private key
+    /// of the certificate. The `tls` feature requires a PKCS#8 key, the `tls-rustls` feature also
+    /// accepts RSA keys. An error is returned if they cannot be parsed, or if no TLS feature is enabled.
+    pub fn identity(&mut self, cert_pem: &[u8], key_pem: &[u8]) -> Result<()> {
+        self.base_settings.identity.0 = Some(tls::parse_identity(cert_pem, key_pem)?);
+        self.base_settings.certificates_changed();
+        Ok(())
+    }
+
+    /// Sets if the connections of this `Session` are kept alive and reused.
+    ///
+    /// Connections are never reused by requests that have a timeout set with `timeout`.
+    ///
+    /// This value defaults to true.
+    pub fn pool_connections(&mut self, pool_connections: bool) {
+        if pool_connections {
+            if self.base_settings.pool.is_none() {
+                self.base_settings.pool = Some(ConnectionPool::with_settings(&self.base_settings));
+            }
+        } else {
+            self.base_settings.pool = None;
+        }
+    }
+
+    /// Sets how long an idle connection is kept in the pool before being closed.
+    ///
+    /// It can be set while connections are not pooled, it applies once they are pooled again.
+    ///
+    /// The default is 90 seconds.
+    pub fn pool_idle_timeout(&mut self, duration: Duration) {
+        self.base_settings.pool_idle_timeout = duration;
+        if let Some(pool) = &self.base_settings.pool {
+            pool.set_idle_timeout(duration);
+        }
+    }
+
+    /// Sets how many idle connections are kept in the pool for each host.
+    ///
+    /// When a connection is given back to a full pool, the connection that stayed idle the longest is closed.
+    /// Setting it to 0 closes every connection once its response is read. It can be set while connections are not
+    /// pooled, it applies once they are pooled again.
+    ///
+    /// The default is 32 connections.
+    pub fn pool_max_idle_per_host(&mut self, max_idle_per_host: usize) {
+        self.base_settings.pool_max_idle_per_host = max_idle_per_host;
+        if let Some(pool) = &self.base_settings.pool {
+            pool.set_max_idle_per_host(max_idle_per_host);
+        }
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::new()
+    }
+}
+
+#[test]
+fn test_session_request() {
+    let mut sess = Session::new();
+    sess.header("X-Test", "hello");
+
+    let prepped = sess.request(Method::DELETE, "http://localhost:1337/foo").prepare();
+    assert_eq!(prepped.method(), Method::DELETE);
+    assert_eq!(prepped.headers()["X-Test"], "hello");
+
+    assert!(sess.try_request(Method::CONNECT, "http://localhost:1337/").is_err());
+    assert!(sess.try_request(Method::GET, "not a url").is_err());
+}
+
+#[test]
+fn test_session_default_headers() {
+    let mut sess = Session::new();
+    sess.header("X-Api-Key", "secret");
+    sess.header("Accept", "application/json");
+    sess.header("X-Tag", "a");
+
+    let prepped = sess
+        .get("http://localhost:1337/foo")
+        .header("Accept", "text/plain")
+        .header_append("X-Tag", "b")
+        .prepare();
+    assert_eq!(prepped.headers()["X-Api-Key"], "secret");
+    assert_eq!(prepped.headers()["Accept"], "text/plain");
+    let tags: Vec<_> = prepped.headers().get_all("X-Tag").iter().collect();
+    assert_eq!(tags, ["a", "b"]);
+
+    let prepped = sess
+        .get("http://localhost:1337/foo")
+        .remove_header("X-Api-Key")
+        .prepare();
+    assert!(prepped.headers().get("X-Api-Key").is_none());
+    assert_eq!(prepped.headers()["Accept"], "application/json");
+}
+
+#[test]
+fn test_session_user_agent() {
+    let mut sess = Session::new();
+    sess.user_agent("foobaz/1.0");
+
+    let prepped = sess.get("http://localhost:1337/foo").prepare();
+    assert_eq!(prepped.headers()[USER_AGENT], "foobaz/1.0");
+
+    let prepped = sess.get("http://localhost:1337/foo").user_agent("other/2.0").prepare();
+    assert_eq!(prepped.headers()[USER_AGENT], "other/2.0");
+}
+
+#[test]
+fn test_session_pool_limits_kept_while_not_pooled() {
+    use std::io::BufReader;
+
+    use crate::pool::PoolKey;
+    use crate::streams::BaseStream;
+
+    let mut sess = Session::new();
+    sess.pool_connections(false);
+    sess.pool_max_idle_per_host(0);
+    sess.pool_connections(true);
+
+    let pool = sess.base_settings.pool.as_ref().unwrap();
+    let url = Url::parse("http://example.com/").unwrap();
+    let key = PoolKey::new(&url, None, &sess.base_settings).unwrap();
+    pool.checkin(key.clone(), BufReader::new(BaseStream::mock(vec![])));
+    assert!(pool.checkout(&key).is_none());
 }
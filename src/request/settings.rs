@@ -1,52 +1,132 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
-use http::HeaderMap;
+use http::{HeaderMap, Version};
 
 #[cfg(feature = "charsets")]
 use crate::charsets::Charset;
+#[cfg(feature = "cookies")]
+use crate::cookies::CookieJar;
+use crate::pool::ConnectionPool;
+#[cfg(feature = "digest")]
+use crate::request::digest_auth::DigestAuth;
 use crate::request::proxy::ProxySettings;
+use crate::request::redirect::RedirectPolicy;
+use crate::request::{RedirectMethodPolicy, RequestTarget, RetryPolicy};
 use crate::skip_debug::SkipDebug;
-use crate::tls::Certificate;
+use crate::tls::{Certificate, Identity, TlsVersion};
 
 #[derive(Clone, Debug)]
 pub struct BaseSettings {
     pub headers: HeaderMap,
+    pub version: Version,
+    pub request_target: RequestTarget,
     pub max_headers: usize,
+    pub max_header_size: usize,
+    pub lenient: bool,
+    pub capture_raw: bool,
+    pub max_body_size: Option<u64>,
+    pub max_bandwidth: Option<u64>,
     pub max_redirections: u32,
     pub follow_redirects: bool,
+    pub redirect_policy: SkipDebug<Option<RedirectPolicy>>,
+    pub allow_downgrade: bool,
+    pub record_history: bool,
+    pub redirect_method_policy: RedirectMethodPolicy,
+    pub retry_policy: RetryPolicy,
     pub connect_timeout: Duration,
     pub read_timeout: Duration,
+    pub write_timeout: Duration,
     pub timeout: Option<Duration>,
+    pub cancel_token: Option<Arc<AtomicBool>>,
     pub proxy_settings: ProxySettings,
+    pub resolve_overrides: HashMap<String, SocketAddr>,
+    pub local_address: Option<IpAddr>,
+    pub tcp_nodelay: bool,
+    pub unix_socket: Option<PathBuf>,
     pub accept_invalid_certs: bool,
     pub accept_invalid_hostnames: bool,
+    pub min_tls_version: TlsVersion,
+    pub sni_hostname: Option<String>,
     pub root_certificates: SkipDebug<Vec<Certificate>>,
+    pub identity: SkipDebug<Option<Identity>>,
+    /// Changes whenever the root certificates or the identity change, since they cannot be compared.
+    pub certificates_id: u64,
+    pub pool: Option<ConnectionPool>,
+    /// Applied to the pool whenever it is created, they are kept while connections are not pooled.
+    pub pool_idle_timeout: Duration,
+    pub pool_max_idle_per_host: usize,
 
     #[cfg(feature = "charsets")]
     pub default_charset: Option<Charset>,
     #[cfg(feature = "compress")]
     pub allow_compression: bool,
+    #[cfg(feature = "cookies")]
+    pub cookie_jar: Option<CookieJar>,
+    #[cfg(feature = "digest")]
+    pub digest_auth: SkipDebug<Option<DigestAuth>>,
+}
+
+impl BaseSettings {
+    /// Record that the root certificates or the identity changed, connections opened before are not reused.
+    pub fn certificates_changed(&mut self) {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        self.certificates_id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 impl Default for BaseSettings {
     fn default() -> Self {
         BaseSettings {
             headers: HeaderMap::new(),
+            version: Version::HTTP_11,
+            request_target: RequestTarget::default(),
             max_headers: 100,
+            max_header_size: 64 * 1024,
+            lenient: false,
+            capture_raw: false,
+            max_body_size: None,
+            max_bandwidth: None,
             max_redirections: 5,
             follow_redirects: true,
+            redirect_policy: SkipDebug(None),
+            allow_downgrade: false,
+            record_history: false,
+            redirect_method_policy: RedirectMethodPolicy::default(),
+            retry_policy: RetryPolicy::default(),
             connect_timeout: Duration::from_secs(30),
             read_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(30),
             timeout: None,
+            cancel_token: None,
             proxy_settings: ProxySettings::from_env(),
+            resolve_overrides: HashMap::new(),
+            local_address: None,
+            tcp_nodelay: true,
+            unix_socket: None,
             accept_invalid_certs: false,
             accept_invalid_hostnames: false,
+            min_tls_version: TlsVersion::default(),
+            sni_hostname: None,
             root_certificates: SkipDebug(Vec::new()),
+            identity: SkipDebug(None),
+            certificates_id: 0,
+            pool: None,
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: 32,
 
             #[cfg(feature = "charsets")]
             default_charset: None,
             #[cfg(feature = "compress")]
             allow_compression: true,
+            #[cfg(feature = "cookies")]
+            cookie_jar: None,
+            #[cfg(feature = "digest")]
+            digest_auth: SkipDebug(None),
         }
     }
 }
@@ -1,19 +1,22 @@
+use std::fmt;
 #[cfg(test)]
 use std::io::Cursor;
 use std::io::{self, Read, Write};
-use std::net::{Shutdown, TcpStream};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
 use std::sync::mpsc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use url::{Host, Url};
 
 use crate::happy;
 use crate::parsing::buffers::BufReaderWrite;
-use crate::parsing::response::parse_response_head;
-use crate::request::BaseSettings;
-use crate::tls::{TlsHandshaker, TlsStream};
-use crate::{ErrorKind, Result};
+use crate::parsing::response::parse_final_response_head;
+use crate::request::{url_basic_auth, BaseSettings};
+use crate::tls::{TlsHandshaker, TlsInfo, TlsStream};
+use crate::{Error, ErrorKind, Result};
 
 pub struct ConnectInfo<'a> {
     pub url: &'a Url,
@@ -22,6 +25,42 @@ pub struct ConnectInfo<'a> {
     pub deadline: Option<Instant>,
 }
 
+/// Details about the connection a response was received on, see `Response::connection_info`.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionInfo {
+    remote_addr: Option<SocketAddr>,
+    tls: Option<TlsInfo>,
+}
+
+impl ConnectionInfo {
+    /// Get the address of the peer of the connection, which is the proxy when one is used.
+    ///
+    /// The address is unknown for connections to Unix sockets.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Get the details of the TLS session with the server, or `None` for plain HTTP.
+    ///
+    /// When tunnelling through a proxy, this is the session with the server, not with the proxy.
+    pub fn tls(&self) -> Option<&TlsInfo> {
+        self.tls.as_ref()
+    }
+}
+
+/// A stream given by the user of the crate, see `PreparedRequest::send_on`.
+pub struct CustomStream(Box<dyn ReadWrite>);
+
+trait ReadWrite: Read + Write + Send {}
+
+impl<T: Read + Write + Send> ReadWrite for T {}
+
+impl fmt::Debug for CustomStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomStream").finish()
+    }
+}
+
 #[derive(Debug)]
 pub enum BaseStream {
     Plain {
@@ -35,12 +74,25 @@ pub enum BaseStream {
     Tunnel {
         stream: Box<TlsStream<BufReaderWrite<BaseStream>>>,
     },
+    #[cfg(unix)]
+    Unix {
+        stream: UnixStream,
+        timeout: Option<mpsc::Sender<()>>,
+    },
+    Custom(CustomStream),
     #[cfg(test)]
     Mock(Cursor<Vec<u8>>),
 }
 
 impl BaseStream {
     pub fn connect(info: &ConnectInfo) -> Result<BaseStream> {
+        #[cfg(unix)]
+        {
+            if let Some(path) = &info.base_settings.unix_socket {
+                return BaseStream::connect_unix(path, info);
+            }
+        }
+
         let connect_url = info.proxy.unwrap_or(info.url);
 
         let host = connect_url.host().ok_or(ErrorKind::InvalidUrlHost)?;
@@ -82,11 +134,14 @@ impl BaseStream {
 
         write!(stream, "CONNECT {}:{} HTTP/1.1\r\n", remote_host, remote_port)?;
         write!(stream, "Host: {}:{}\r\n", proxy_host, proxy_port)?;
+        if let Some(auth) = url_basic_auth(proxy_url) {
+            write!(stream, "Proxy-Authorization: {}\r\n", auth)?;
+        }
         write!(stream, "Connection: close\r\n")?;
         write!(stream, "\r\n")?;
 
         let mut stream = BufReaderWrite::new(stream);
-        let (status, _) = parse_response_head(&mut stream, base_settings.max_headers)?;
+        let status = parse_final_response_head(&mut stream, base_settings)?.status;
 
         if !status.is_success() {
             // Error initializaing tunnel, get status code and up to 10 KiB of data from the body.
@@ -101,7 +156,8 @@ impl BaseStream {
 
         let mut handshaker = TlsHandshaker::new();
         apply_base_settings(&mut handshaker, base_settings)?;
-        let stream = handshaker.handshake(remote_host, stream)?;
+        let domain = base_settings.sni_hostname.as_deref().unwrap_or(remote_host);
+        let stream = handshaker.handshake(domain, stream)?;
 
         Ok(BaseStream::Tunnel {
             stream: Box::new(stream),
@@ -109,38 +165,150 @@ impl BaseStream {
     }
 
     fn connect_tcp(host: &Host<&str>, port: u16, info: &ConnectInfo) -> Result<(TcpStream, Option<mpsc::Sender<()>>)> {
-        let stream = happy::connect(host, port, info.base_settings.connect_timeout, info.deadline)?;
+        let overridden = match host {
+            Host::Domain(domain) => info.base_settings.resolve_overrides.get(&domain.to_ascii_lowercase()),
+            _ => None,
+        };
+        let local_addr = info.base_settings.local_address;
+        let connect_timeout = info.base_settings.connect_timeout;
+        let stream = match overridden {
+            Some(addr) => {
+                debug!("{} is resolved to {}", host, addr);
+                let ip = match addr.ip() {
+                    IpAddr::V4(ip) => Host::Ipv4(ip),
+                    IpAddr::V6(ip) => Host::Ipv6(ip),
+                };
+                happy::connect(&ip, addr.port(), local_addr, connect_timeout, info.deadline)?
+            }
+            None => happy::connect(host, port, local_addr, connect_timeout, info.deadline)?,
+        };
         stream.set_read_timeout(Some(info.base_settings.read_timeout))?;
+        stream.set_write_timeout(Some(info.base_settings.write_timeout))?;
+        stream.set_nodelay(info.base_settings.tcp_nodelay)?;
         let timeout = info
             .deadline
             .map(|deadline| -> Result<mpsc::Sender<()>> {
                 let stream = stream.try_clone()?;
-                let (tx, rx) = mpsc::channel();
-                thread::spawn(move || {
-                    let shutdown = match deadline.checked_duration_since(Instant::now()) {
-                        Some(timeout) => rx.recv_timeout(timeout) == Err(mpsc::RecvTimeoutError::Timeout),
-                        None => rx.try_recv() == Err(mpsc::TryRecvError::Empty),
-                    };
-
-                    if shutdown {
-                        drop(rx);
-                        let _ = stream.shutdown(Shutdown::Both);
-                    }
-                });
-                Ok(tx)
+                Ok(shutdown_at(deadline, move || {
+                    let _ = stream.shutdown(Shutdown::Both);
+                }))
             })
             .transpose()?;
         Ok((stream, timeout))
     }
 
+    #[cfg(unix)]
+    fn connect_unix(path: &std::path::Path, info: &ConnectInfo) -> Result<BaseStream> {
+        // Only plain HTTP is spoken over a Unix socket, the host of the URL is only used for the Host header.
+        if info.url.scheme() != "http" {
+            return Err(ErrorKind::InvalidBaseUrl.into());
+        }
+
+        debug!("trying to connect to {}", path.display());
+
+        let stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(info.base_settings.read_timeout))?;
+        stream.set_write_timeout(Some(info.base_settings.write_timeout))?;
+        let timeout = info
+            .deadline
+            .map(|deadline| -> Result<mpsc::Sender<()>> {
+                let stream = stream.try_clone()?;
+                Ok(shutdown_at(deadline, move || {
+                    let _ = stream.shutdown(Shutdown::Both);
+                }))
+            })
+            .transpose()?;
+        Ok(BaseStream::Unix { stream, timeout })
+    }
+
     fn connect_tls(host: &Host<&str>, port: u16, info: &ConnectInfo) -> Result<BaseStream> {
         let (stream, timeout) = BaseStream::connect_tcp(host, port, info)?;
         let mut handshaker = TlsHandshaker::new();
         apply_base_settings(&mut handshaker, info.base_settings)?;
-        let stream = handshaker.handshake(&host.to_string(), stream)?;
+        // The name of an HTTPS proxy is never overridden, only the name of the server.
+        let domain = match &info.base_settings.sni_hostname {
+            Some(name) if info.proxy.is_none() => name.clone(),
+            _ => host.to_string(),
+        };
+        let stream = handshaker.handshake(&domain, stream)?;
         Ok(BaseStream::Tls { stream, timeout })
     }
 
+    /// Get the details about this connection.
+    pub fn connection_info(&self) -> ConnectionInfo {
+        match self {
+            BaseStream::Plain { stream, .. } => ConnectionInfo {
+                remote_addr: stream.peer_addr().ok(),
+                tls: None,
+            },
+            BaseStream::Tls { stream, .. } => ConnectionInfo {
+                remote_addr: stream.get_ref().peer_addr().ok(),
+                tls: Some(stream.info()),
+            },
+            BaseStream::Tunnel { stream } => ConnectionInfo {
+                tls: Some(stream.info()),
+                ..stream.get_ref().get_ref().connection_info()
+            },
+            #[cfg(unix)]
+            BaseStream::Unix { .. } => ConnectionInfo::default(),
+            BaseStream::Custom(_) => ConnectionInfo::default(),
+            #[cfg(test)]
+            BaseStream::Mock(_) => ConnectionInfo::default(),
+        }
+    }
+
+    /// Change the read timeout of the underlying socket.
+    pub fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            BaseStream::Plain { stream, .. } => stream.set_read_timeout(Some(timeout)),
+            BaseStream::Tls { stream, .. } => stream.get_ref().set_read_timeout(Some(timeout)),
+            BaseStream::Tunnel { stream } => stream.get_ref().get_ref().set_read_timeout(timeout),
+            #[cfg(unix)]
+            BaseStream::Unix { stream, .. } => stream.set_read_timeout(Some(timeout)),
+            BaseStream::Custom(_) => Ok(()),
+            #[cfg(test)]
+            BaseStream::Mock(_) => Ok(()),
+        }
+    }
+
+    /// Change the write timeout of the underlying socket.
+    pub fn set_write_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            BaseStream::Plain { stream, .. } => stream.set_write_timeout(Some(timeout)),
+            BaseStream::Tls { stream, .. } => stream.get_ref().set_write_timeout(Some(timeout)),
+            BaseStream::Tunnel { stream } => stream.get_ref().get_ref().set_write_timeout(timeout),
+            #[cfg(unix)]
+            BaseStream::Unix { stream, .. } => stream.set_write_timeout(Some(timeout)),
+            BaseStream::Custom(_) => Ok(()),
+            #[cfg(test)]
+            BaseStream::Mock(_) => Ok(()),
+        }
+    }
+
+    /// Check if an idle connection can still be used to send a request, without blocking.
+    ///
+    /// The server never sends anything on an idle connection. If it closed the connection or sent data
+    /// anyway, the connection is unusable and whatever was read does not matter.
+    pub fn is_idle_alive(&self) -> bool {
+        match self {
+            BaseStream::Plain { stream, .. } => is_idle_alive(stream, TcpStream::set_nonblocking),
+            BaseStream::Tls { stream, .. } => is_idle_alive(stream.get_ref(), TcpStream::set_nonblocking),
+            BaseStream::Tunnel { stream } => stream.get_ref().get_ref().is_idle_alive(),
+            #[cfg(unix)]
+            BaseStream::Unix { stream, .. } => is_idle_alive(stream, UnixStream::set_nonblocking),
+            BaseStream::Custom(_) => false,
+            #[cfg(test)]
+            BaseStream::Mock(_) => true,
+        }
+    }
+
+    pub fn custom<S>(stream: S) -> BaseStream
+    where
+        S: Read + Write + Send + 'static,
+    {
+        BaseStream::Custom(CustomStream(Box::new(stream)))
+    }
+
     #[cfg(test)]
     pub fn mock(bytes: Vec<u8>) -> BaseStream {
         BaseStream::Mock(Cursor::new(bytes))
@@ -154,6 +322,9 @@ impl Read for BaseStream {
             BaseStream::Plain { stream, timeout } => read_timeout(stream, buf, timeout),
             BaseStream::Tls { stream, timeout } => read_timeout(stream, buf, timeout),
             BaseStream::Tunnel { stream } => stream.read(buf),
+            #[cfg(unix)]
+            BaseStream::Unix { stream, timeout } => read_timeout(stream, buf, timeout),
+            BaseStream::Custom(CustomStream(stream)) => stream.read(buf),
             #[cfg(test)]
             BaseStream::Mock(s) => s.read(buf),
         }
@@ -164,9 +335,12 @@ impl Write for BaseStream {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self {
-            BaseStream::Plain { stream, .. } => stream.write(buf),
-            BaseStream::Tls { stream, .. } => stream.write(buf),
+            BaseStream::Plain { stream, timeout } => write_timeout(stream, buf, timeout),
+            BaseStream::Tls { stream, timeout } => write_timeout(stream, buf, timeout),
             BaseStream::Tunnel { stream } => stream.write(buf),
+            #[cfg(unix)]
+            BaseStream::Unix { stream, timeout } => write_timeout(stream, buf, timeout),
+            BaseStream::Custom(CustomStream(stream)) => stream.write(buf),
             #[cfg(test)]
             _ => Ok(0),
         }
@@ -178,19 +352,47 @@ impl Write for BaseStream {
             BaseStream::Plain { stream, .. } => stream.flush(),
             BaseStream::Tls { stream, .. } => stream.flush(),
             BaseStream::Tunnel { stream } => stream.flush(),
+            #[cfg(unix)]
+            BaseStream::Unix { stream, .. } => stream.flush(),
+            BaseStream::Custom(CustomStream(stream)) => stream.flush(),
             #[cfg(test)]
             _ => Ok(()),
         }
     }
 }
 
+/// Spawn a thread which calls `shutdown` once the deadline is reached, unless a message is sent first.
+fn shutdown_at<F>(deadline: Instant, shutdown: F) -> mpsc::Sender<()>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let expired = match deadline.checked_duration_since(Instant::now()) {
+            Some(timeout) => rx.recv_timeout(timeout) == Err(mpsc::RecvTimeoutError::Timeout),
+            None => rx.try_recv() == Err(mpsc::TryRecvError::Empty),
+        };
+
+        if expired {
+            drop(rx);
+            shutdown();
+        }
+    });
+    tx
+}
+
+/// The error returned by reads and writes which reached a timeout, it becomes `ErrorKind::Timeout`.
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, Error::from(ErrorKind::Timeout))
+}
+
 fn read_timeout(stream: &mut impl Read, buf: &mut [u8], timeout: &Option<mpsc::Sender<()>>) -> io::Result<usize> {
     match stream.read(buf) {
         Ok(0) => {
             if let Some(timeout) = timeout {
                 // On Unix we get a 0 read when the connection is shutdown by the timeout thread.
                 if !buf.is_empty() && timeout.send(()).is_err() {
-                    return Err(io::ErrorKind::TimedOut.into());
+                    return Err(timed_out());
                 }
             }
             Ok(0)
@@ -200,7 +402,7 @@ fn read_timeout(stream: &mut impl Read, buf: &mut [u8], timeout: &Option<mpsc::S
             if let Some(timeout) = timeout {
                 // On Windows we get a ConnectionReset when the connection is shutdown by the timeout thread.
                 if err.kind() == io::ErrorKind::ConnectionReset && timeout.send(()).is_err() {
-                    return Err(io::ErrorKind::TimedOut.into());
+                    return Err(timed_out());
                 }
             }
             Err(err)
@@ -208,11 +410,70 @@ fn read_timeout(stream: &mut impl Read, buf: &mut [u8], timeout: &Option<mpsc::S
     }
 }
 
+fn write_timeout(stream: &mut impl Write, buf: &[u8], timeout: &Option<mpsc::Sender<()>>) -> io::Result<usize> {
+    match stream.write(buf) {
+        // Sockets are blocking, a write only stops waiting for the peer once the write timeout is reached.
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => Err(timed_out()),
+        Err(err) => {
+            if let Some(timeout) = timeout {
+                // Writes fail with a broken pipe or a reset once the connection is shutdown by the timeout thread.
+                if timeout.send(()).is_err() {
+                    return Err(timed_out());
+                }
+            }
+            Err(err)
+        }
+        res => res,
+    }
+}
+
+fn is_idle_alive<S>(stream: &S, set_nonblocking: fn(&S, bool) -> io::Result<()>) -> bool
+where
+    for<'a> &'a S: Read,
+{
+    if set_nonblocking(stream, true).is_err() {
+        return false;
+    }
+    let res = { stream }.read(&mut [0]);
+    // A socket left in non-blocking mode would break the next request.
+    if set_nonblocking(stream, false).is_err() {
+        return false;
+    }
+    matches!(res, Err(err) if err.kind() == io::ErrorKind::WouldBlock)
+}
+
 fn apply_base_settings(handshaker: &mut TlsHandshaker, base_settings: &BaseSettings) -> Result<()> {
     handshaker.danger_accept_invalid_certs(base_settings.accept_invalid_certs);
     handshaker.danger_accept_invalid_hostnames(base_settings.accept_invalid_hostnames);
+    handshaker.set_min_tls_version(base_settings.min_tls_version);
     for cert in &base_settings.root_certificates.0 {
-        handshaker.add_root_certificate(cert.clone())?;
+        handshaker.add_root_certificate(cert)?;
+    }
+    if let Some(identity) = &base_settings.identity.0 {
+        handshaker.set_identity(identity)?;
     }
     Ok(())
 }
+
+#[test]
+fn test_connect_tcp_nodelay() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = Url::parse(&format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port())).unwrap();
+
+    for &tcp_nodelay in &[true, false] {
+        let base_settings = BaseSettings {
+            tcp_nodelay,
+            ..BaseSettings::default()
+        };
+        let info = ConnectInfo {
+            url: &url,
+            proxy: None,
+            base_settings: &base_settings,
+            deadline: None,
+        };
+        match BaseStream::connect(&info).unwrap() {
+            BaseStream::Plain { stream, .. } => assert_eq!(stream.nodelay().unwrap(), tcp_nodelay),
+            _ => panic!("expected a plain stream"),
+        }
+    }
+}
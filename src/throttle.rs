@@ -0,0 +1,104 @@
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A token bucket limiting the number of bytes transferred per second.
+///
+/// This is best-effort: transfers are cut into bursts of a tenth of the rate and the thread sleeps once the
+/// bucket is empty, so the rate is only respected on average.
+#[derive(Debug)]
+pub struct Throttle {
+    bytes_per_sec: u64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_sec: u64) -> Throttle {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        let mut throttle = Throttle {
+            bytes_per_sec,
+            tokens: 0.0,
+            last: Instant::now(),
+        };
+        throttle.tokens = throttle.burst() as f64;
+        throttle
+    }
+
+    fn burst(&self) -> usize {
+        (self.bytes_per_sec / 10).clamp(1, usize::MAX as u64) as usize
+    }
+
+    /// Get the number of bytes which can be transferred at once out of `len`.
+    pub fn limit(&self, len: usize) -> usize {
+        len.min(self.burst())
+    }
+
+    /// Record that `len` bytes were transferred, sleeping if the rate was exceeded.
+    pub fn consume(&mut self, len: usize) {
+        let now = Instant::now();
+        let refill = now.duration_since(self.last).as_secs_f64() * self.bytes_per_sec as f64;
+        self.tokens = (self.tokens + refill).min(self.burst() as f64) - len as f64;
+        self.last = now;
+
+        if self.tokens < 0.0 {
+            thread::sleep(Duration::from_secs_f64(-self.tokens / self.bytes_per_sec as f64));
+        }
+    }
+}
+
+/// A writer which is throttled if a rate is given.
+pub struct Throttled<W> {
+    inner: W,
+    throttle: Option<Throttle>,
+}
+
+impl<W> Throttled<W> {
+    pub fn new(inner: W, bytes_per_sec: Option<u64>) -> Throttled<W> {
+        Throttled {
+            inner,
+            throttle: bytes_per_sec.map(Throttle::new),
+        }
+    }
+}
+
+impl<W: Write> Write for Throttled<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.throttle {
+            Some(throttle) => {
+                let n = self.inner.write(&buf[..throttle.limit(buf.len())])?;
+                throttle.consume(n);
+                Ok(n)
+            }
+            None => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[test]
+fn test_throttle_limit() {
+    let throttle = Throttle::new(1000);
+    assert_eq!(throttle.limit(5000), 100);
+    assert_eq!(throttle.limit(10), 10);
+    assert_eq!(Throttle::new(0).limit(10), 1);
+}
+
+#[test]
+fn test_throttled_write() {
+    let start = Instant::now();
+    let mut writer = Throttled::new(Vec::new(), Some(2000));
+    writer.write_all(&[0; 1000]).unwrap();
+    assert_eq!(writer.inner.len(), 1000);
+    // The first burst of 200 bytes is free, the remaining 800 bytes take 400ms.
+    assert!(start.elapsed() >= Duration::from_millis(350));
+}
+
+#[test]
+fn test_unthrottled_write() {
+    let mut writer = Throttled::new(Vec::new(), None);
+    assert_eq!(writer.write(&[0; 1000]).unwrap(), 1000);
+}
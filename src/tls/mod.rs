@@ -15,3 +15,45 @@ pub use rustls_impl::*;
 
 #[cfg(all(not(feature = "tls"), not(feature = "tls-rustls")))]
 pub use no_tls_impl::*;
+
+/// The versions of the TLS protocol, independently of the TLS backend enabled by features.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum TlsVersion {
+    /// TLS 1.0, deprecated by RFC 8996.
+    Tls10,
+    /// TLS 1.1, deprecated by RFC 8996.
+    Tls11,
+    /// TLS 1.2.
+    #[default]
+    Tls12,
+    /// TLS 1.3.
+    Tls13,
+}
+
+/// Details about the TLS session of a connection, see `ConnectionInfo::tls`.
+///
+/// Each detail is only known if the TLS backend enabled by features reports it. The `tls` feature cannot report
+/// the version and the cipher suite.
+#[derive(Clone, Debug, Default)]
+pub struct TlsInfo {
+    pub(crate) version: Option<TlsVersion>,
+    pub(crate) cipher_suite: Option<String>,
+    pub(crate) peer_certificate: Option<Vec<u8>>,
+}
+
+impl TlsInfo {
+    /// Get the version of the TLS protocol negotiated with the server.
+    pub fn version(&self) -> Option<TlsVersion> {
+        self.version
+    }
+
+    /// Get the name of the cipher suite negotiated with the server, such as `TLS13_AES_128_GCM_SHA256`.
+    pub fn cipher_suite(&self) -> Option<&str> {
+        self.cipher_suite.as_deref()
+    }
+
+    /// Get the certificate presented by the server, DER encoded.
+    pub fn peer_certificate(&self) -> Option<&[u8]> {
+        self.peer_certificate.as_deref()
+    }
+}
@@ -2,12 +2,20 @@ use std::fmt;
 use std::io;
 use std::io::prelude::*;
 
-use native_tls::HandshakeError;
+use native_tls::{HandshakeError, Protocol};
 
+use super::{TlsInfo, TlsVersion};
 use crate::Result;
 
 pub type Certificate = native_tls::Certificate;
 
+pub type Identity = native_tls::Identity;
+
+/// Parse a PEM encoded certificate chain and its PKCS#8 PEM encoded private key.
+pub fn parse_identity(cert_pem: &[u8], key_pem: &[u8]) -> Result<Identity> {
+    Ok(native_tls::Identity::from_pkcs8(cert_pem, key_pem)?)
+}
+
 pub struct TlsHandshaker {
     inner: native_tls::TlsConnectorBuilder,
 }
@@ -27,8 +35,23 @@ impl TlsHandshaker {
         self.inner.danger_accept_invalid_hostnames(accept_invalid_hostnames);
     }
 
-    pub fn add_root_certificate(&mut self, cert: Certificate) -> Result<()> {
-        self.inner.add_root_certificate(cert);
+    pub fn set_min_tls_version(&mut self, version: TlsVersion) {
+        let protocol = match version {
+            TlsVersion::Tls10 => Protocol::Tlsv10,
+            TlsVersion::Tls11 => Protocol::Tlsv11,
+            TlsVersion::Tls12 => Protocol::Tlsv12,
+            TlsVersion::Tls13 => Protocol::Tlsv13,
+        };
+        self.inner.min_protocol_version(Some(protocol));
+    }
+
+    pub fn add_root_certificate(&mut self, cert: &Certificate) -> Result<()> {
+        self.inner.add_root_certificate(cert.clone());
+        Ok(())
+    }
+
+    pub fn set_identity(&mut self, identity: &Identity) -> Result<()> {
+        self.inner.identity(identity.clone());
         Ok(())
     }
 
@@ -37,7 +60,7 @@ impl TlsHandshaker {
         S: Read + Write,
     {
         let connector = self.inner.build()?;
-        let stream = match connector.connect(&domain, stream) {
+        let stream = match connector.connect(domain, stream) {
             Ok(stream) => stream,
             Err(HandshakeError::Failure(err)) => return Err(err.into()),
             Err(HandshakeError::WouldBlock(mut stream)) => loop {
@@ -59,6 +82,26 @@ where
     inner: native_tls::TlsStream<S>,
 }
 
+impl<S> TlsStream<S>
+where
+    S: Read + Write,
+{
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+
+    pub fn info(&self) -> TlsInfo {
+        let peer_certificate = match self.inner.peer_certificate() {
+            Ok(cert) => cert.and_then(|cert| cert.to_der().ok()),
+            Err(_) => None,
+        };
+        TlsInfo {
+            peer_certificate,
+            ..TlsInfo::default()
+        }
+    }
+}
+
 impl<S> Read for TlsStream<S>
 where
     S: Read + Write,
@@ -3,10 +3,17 @@ use std::io;
 use std::io::prelude::*;
 use std::marker::PhantomData;
 
+use super::{TlsInfo, TlsVersion};
 use crate::{ErrorKind, Result};
 
 pub type Certificate = ();
 
+pub type Identity = ();
+
+pub fn parse_identity(_cert_pem: &[u8], _key_pem: &[u8]) -> Result<Identity> {
+    Err(ErrorKind::TlsDisabled.into())
+}
+
 pub struct TlsHandshaker {}
 
 impl TlsHandshaker {
@@ -18,7 +25,13 @@ impl TlsHandshaker {
 
     pub fn danger_accept_invalid_hostnames(&mut self, _accept_invalid_hostnames: bool) {}
 
-    pub fn add_root_certificate(&mut self, _cert: Certificate) -> Result<()> {
+    pub fn set_min_tls_version(&mut self, _version: TlsVersion) {}
+
+    pub fn add_root_certificate(&mut self, _cert: &Certificate) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_identity(&mut self, _identity: &Identity) -> Result<()> {
         Ok(())
     }
 
@@ -37,6 +50,19 @@ where
     dummy: PhantomData<S>,
 }
 
+impl<S> TlsStream<S>
+where
+    S: Read + Write,
+{
+    pub fn get_ref(&self) -> &S {
+        unreachable!("TLS streams cannot be created when TLS is disabled")
+    }
+
+    pub fn info(&self) -> TlsInfo {
+        unreachable!("TLS streams cannot be created when TLS is disabled")
+    }
+}
+
 impl<S> Read for TlsStream<S>
 where
     S: Read + Write,
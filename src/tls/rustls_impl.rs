@@ -3,16 +3,43 @@ use std::io;
 use std::io::prelude::*;
 use std::sync::Arc;
 
+use rustls::internal::pemfile;
 use rustls::{
-    ClientConfig, ClientSession, ServerCertVerified, ServerCertVerifier, Session, StreamOwned, WebPKIVerifier,
+    ClientConfig, ClientSession, PrivateKey, ProtocolVersion, ServerCertVerified, ServerCertVerifier, Session,
+    StreamOwned, WebPKIVerifier,
 };
 use webpki::DNSNameRef;
 use webpki_roots::TLS_SERVER_ROOTS;
 
-use crate::Result;
+use super::{TlsInfo, TlsVersion};
+use crate::{ErrorKind, Result};
 
 pub type Certificate = rustls::Certificate;
 
+#[derive(Clone)]
+pub struct Identity {
+    certs: Vec<Certificate>,
+    key: PrivateKey,
+}
+
+/// Parse a PEM encoded certificate chain and its PKCS#8 or RSA PEM encoded private key.
+pub fn parse_identity(cert_pem: &[u8], key_pem: &[u8]) -> Result<Identity> {
+    let certs = pemfile::certs(&mut &cert_pem[..]).map_err(|_| ErrorKind::InvalidIdentity)?;
+    let mut keys = pemfile::pkcs8_private_keys(&mut &key_pem[..]).map_err(|_| ErrorKind::InvalidIdentity)?;
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut &key_pem[..]).map_err(|_| ErrorKind::InvalidIdentity)?;
+    }
+
+    let key = match keys.into_iter().next() {
+        Some(key) if !certs.is_empty() => key,
+        _ => return Err(ErrorKind::InvalidIdentity.into()),
+    };
+    // Make sure the key can actually be used for signing before any connection is attempted.
+    rustls::sign::any_supported_type(&key).map_err(|_| ErrorKind::InvalidIdentity)?;
+
+    Ok(Identity { certs, key })
+}
+
 pub struct TlsHandshaker {
     inner: ClientConfig,
     accept_invalid_certs: bool,
@@ -51,8 +78,23 @@ impl TlsHandshaker {
             }))
     }
 
-    pub fn add_root_certificate(&mut self, cert: Certificate) -> Result<()> {
-        self.inner.root_store.add(&cert)?;
+    /// rustls only implements TLS 1.2 and 1.3, older versions are never used.
+    pub fn set_min_tls_version(&mut self, version: TlsVersion) {
+        self.inner.versions = match version {
+            TlsVersion::Tls13 => vec![ProtocolVersion::TLSv1_3],
+            _ => vec![ProtocolVersion::TLSv1_3, ProtocolVersion::TLSv1_2],
+        };
+    }
+
+    pub fn add_root_certificate(&mut self, cert: &Certificate) -> Result<()> {
+        self.inner.root_store.add(cert)?;
+        Ok(())
+    }
+
+    pub fn set_identity(&mut self, identity: &Identity) -> Result<()> {
+        self.inner
+            .set_single_client_cert(identity.certs.clone(), identity.key.clone())
+            .map_err(|_| ErrorKind::InvalidIdentity)?;
         Ok(())
     }
 
@@ -87,6 +129,31 @@ impl<S> TlsStream<S>
 where
     S: Read + Write,
 {
+    pub fn get_ref(&self) -> &S {
+        &self.inner.sock
+    }
+
+    pub fn info(&self) -> TlsInfo {
+        let sess = &self.inner.sess;
+        let version = sess.get_protocol_version().and_then(|version| match version {
+            ProtocolVersion::TLSv1_0 => Some(TlsVersion::Tls10),
+            ProtocolVersion::TLSv1_1 => Some(TlsVersion::Tls11),
+            ProtocolVersion::TLSv1_2 => Some(TlsVersion::Tls12),
+            ProtocolVersion::TLSv1_3 => Some(TlsVersion::Tls13),
+            _ => None,
+        });
+        TlsInfo {
+            version,
+            cipher_suite: sess
+                .get_negotiated_ciphersuite()
+                .map(|suite| format!("{:?}", suite.suite)),
+            peer_certificate: sess
+                .get_peer_certificates()
+                .and_then(|certs| certs.into_iter().next())
+                .map(|cert| cert.0),
+        }
+    }
+
     fn handle_close_notify(&mut self, res: io::Result<usize>) -> io::Result<usize> {
         match res {
             Err(err) if err.kind() == io::ErrorKind::ConnectionAborted => {
@@ -0,0 +1,48 @@
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+mod tools;
+
+#[test]
+fn test_cancel_before_send() {
+    let token = Arc::new(AtomicBool::new(true));
+    // Nothing listens on this port, the request must fail before connecting.
+    let err = attohttpc::get("http://127.0.0.1:1")
+        .cancel_token(token)
+        .send()
+        .unwrap_err();
+    assert!(matches!(err.kind(), attohttpc::ErrorKind::Cancelled));
+}
+
+#[test]
+fn test_cancel_body() {
+    let (port, thread) = tools::start_raw_server(1, |mut reader| {
+        tools::read_head(&mut reader);
+        let stream = reader.get_mut();
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000000\r\n\r\n")
+            .unwrap();
+        // Write part of the body and wait for the client to go away.
+        let _ = stream.write_all(&[b'a'; 1000]);
+        let _ = stream.read_to_end(&mut Vec::new());
+    });
+
+    let token = Arc::new(AtomicBool::new(false));
+    let resp = attohttpc::get(format!("http://localhost:{}", port))
+        .cancel_token(token.clone())
+        .send()
+        .unwrap();
+    let mut reader = resp.split().2;
+
+    let mut buf = [0; 10];
+    reader.read_exact(&mut buf).unwrap();
+    token.store(true, Ordering::Relaxed);
+
+    let err = reader.read(&mut buf).unwrap_err();
+    let err = attohttpc::Error::from(err);
+    assert!(matches!(err.kind(), attohttpc::ErrorKind::Cancelled));
+
+    drop(reader);
+    thread.join().unwrap();
+}
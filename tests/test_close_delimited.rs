@@ -0,0 +1,49 @@
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+mod tools;
+
+/// Start a server which answers each of `connections` connections with a body delimited by closing the connection.
+fn start_server(head: &'static [u8], connections: usize) -> (u16, thread::JoinHandle<Vec<()>>) {
+    tools::start_raw_server(connections, move |mut reader| {
+        tools::read_head(&mut reader);
+
+        let stream = reader.get_mut();
+        stream.write_all(head).unwrap();
+        stream.write_all(b"hel").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        stream.write_all(b"lo").unwrap();
+    })
+}
+
+#[test]
+fn test_http_10_body_read_until_close() {
+    let (port, thread) = start_server(b"HTTP/1.0 200 OK\r\n\r\n", 1);
+
+    let resp = attohttpc::get(format!("http://localhost:{}", port)).send().unwrap();
+    assert_eq!(resp.text().unwrap(), "hello");
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_connection_close_body_read_until_close() {
+    let (port, thread) = start_server(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n", 1);
+
+    let resp = attohttpc::get(format!("http://localhost:{}", port)).send().unwrap();
+    assert_eq!(resp.text().unwrap(), "hello");
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_close_delimited_connection_not_reused() {
+    // Without a length, the end of the body is only known once the connection is closed.
+    let (port, thread) = start_server(b"HTTP/1.1 200 OK\r\n\r\n", 2);
+
+    let sess = attohttpc::Session::new();
+    for _ in 0..2 {
+        let resp = sess.get(format!("http://localhost:{}", port)).send().unwrap();
+        assert_eq!(resp.text().unwrap(), "hello");
+    }
+    thread.join().unwrap();
+}
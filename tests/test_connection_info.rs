@@ -0,0 +1,57 @@
+mod tools;
+
+#[tokio::test(threaded_scheduler)]
+async fn test_connection_info_http() -> Result<(), anyhow::Error> {
+    let port = tools::start_hello_world_server(false).await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}", port)).send()?;
+    let info = resp.connection_info();
+    let addr = info.remote_addr().unwrap();
+    assert!(addr.ip().is_loopback());
+    assert_eq!(addr.port(), port);
+    assert!(info.tls().is_none());
+
+    Ok(())
+}
+
+#[cfg(any(feature = "tls", feature = "tls-rustls"))]
+#[tokio::test(threaded_scheduler)]
+async fn test_connection_info_https() -> Result<(), anyhow::Error> {
+    let port = tools::start_hello_world_server(true).await?;
+
+    let resp = attohttpc::get(format!("https://localhost:{}", port))
+        .danger_accept_invalid_certs(true)
+        .send()?;
+    let info = resp.connection_info();
+    assert_eq!(info.remote_addr().unwrap().port(), port);
+
+    let tls = info.tls().unwrap();
+    // A DER encoded certificate is an ASN.1 sequence.
+    assert_eq!(tls.peer_certificate().unwrap()[0], 0x30);
+    #[cfg(not(feature = "tls"))]
+    {
+        assert!(tls.version().unwrap() >= attohttpc::TlsVersion::Tls12);
+        assert!(tls.cipher_suite().is_some());
+    }
+
+    Ok(())
+}
+
+#[cfg(any(feature = "tls", feature = "tls-rustls"))]
+#[tokio::test(threaded_scheduler)]
+async fn test_connection_info_tunnel() -> Result<(), anyhow::Error> {
+    let remote_port = tools::start_hello_world_server(true).await?;
+    let proxy_port = tools::start_proxy_server(false).await?;
+    let proxy_url = url::Url::parse(&format!("http://localhost:{}", proxy_port)).unwrap();
+
+    let settings = attohttpc::ProxySettingsBuilder::new().https_proxy(proxy_url).build();
+    let resp = attohttpc::get(format!("https://localhost:{}", remote_port))
+        .proxy_settings(settings)
+        .danger_accept_invalid_certs(true)
+        .send()?;
+    let info = resp.connection_info();
+    assert_eq!(info.remote_addr().unwrap().port(), proxy_port);
+    assert!(info.tls().unwrap().peer_certificate().is_some());
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "cookies")]
+use std::net::SocketAddr;
+
+use attohttpc::CookieJar;
+use warp::Filter;
+
+async fn make_server() -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let a = warp::path("login").map(|| {
+        http::Response::builder()
+            .header("Location", "/echo")
+            .header("Set-Cookie", "session=abc; Path=/")
+            .header("Set-Cookie", "theme=dark; Path=/")
+            .status(http::StatusCode::FOUND)
+            .body("")
+    });
+    let b = warp::path("logout").map(|| {
+        http::Response::builder()
+            .header("Set-Cookie", "session=; Path=/; Max-Age=0")
+            .body("")
+    });
+    let c = warp::path("echo")
+        .and(warp::header::optional::<String>("cookie"))
+        .map(|cookie: Option<String>| cookie.unwrap_or_default());
+
+    let server = warp::serve(a.or(b).or(c)).serve_incoming(incoming);
+    tokio::spawn(server);
+
+    Ok(local_addr.port())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_cookies_sent_after_redirection() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let jar = CookieJar::new();
+    let resp = attohttpc::get(format!("http://localhost:{}/login", port))
+        .cookie_jar(jar.clone())
+        .send()?;
+    assert_eq!(resp.text()?, "session=abc; theme=dark");
+    assert_eq!(jar.cookies(format!("http://localhost:{}/", port))?.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_cookies_session() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let mut sess = attohttpc::Session::new();
+    sess.cookie_jar(CookieJar::new());
+    sess.header("Cookie", "user=1");
+
+    sess.get(format!("http://localhost:{}/login", port)).send()?.text()?;
+    let resp = sess.get(format!("http://localhost:{}/echo", port)).send()?;
+    assert_eq!(resp.text()?, "user=1; session=abc; theme=dark");
+
+    sess.get(format!("http://localhost:{}/logout", port)).send()?.text()?;
+    let resp = sess.get(format!("http://localhost:{}/echo", port)).send()?;
+    assert_eq!(resp.text()?, "user=1; theme=dark");
+
+    Ok(())
+}
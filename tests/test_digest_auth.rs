@@ -0,0 +1,124 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use attohttpc::StatusCode;
+use hyper::server::conn::AddrIncoming;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+
+/// Start a server which challenges requests without an `Authorization` header. Requests with a digest
+/// `Authorization` header are answered with the header, or with a new challenge if `always_refuse` is set.
+async fn make_server(always_refuse: bool, requests: Arc<AtomicUsize>) -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let bound = AddrIncoming::bind(&addr)?;
+    let port = bound.local_addr().port();
+
+    let make_service = make_service_fn(move |_| {
+        let requests = requests.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                let authorization = req
+                    .headers()
+                    .get("authorization")
+                    .map(|val| val.to_str().unwrap().to_owned());
+                async move {
+                    let resp = match authorization {
+                        Some(authorization) if !always_refuse => Response::builder().body(Body::from(authorization)),
+                        _ => Response::builder()
+                            .status(401)
+                            .header(
+                                "WWW-Authenticate",
+                                r#"Digest realm="test", qop="auth", nonce="abc", opaque="xyz""#,
+                            )
+                            .body(Body::empty()),
+                    };
+                    Ok::<_, http::Error>(resp.unwrap())
+                }
+            }))
+        }
+    });
+    tokio::spawn(Server::builder(bound).serve(make_service));
+
+    Ok(port)
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_digest_auth() -> Result<(), anyhow::Error> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let port = make_server(false, requests.clone()).await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}/protected?a=1", port))
+        .digest_auth("user", "pass")
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let authorization = resp.text()?;
+    assert!(authorization.starts_with(r#"Digest username="user", realm="test", nonce="abc", uri="/protected?a=1""#));
+    assert!(authorization.contains("qop=auth, nc=00000001"));
+    assert!(authorization.contains(r#"opaque="xyz""#));
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_digest_auth_refused() -> Result<(), anyhow::Error> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let port = make_server(true, requests.clone()).await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}/protected", port))
+        .digest_auth("user", "wrong")
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_no_digest_auth() -> Result<(), anyhow::Error> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let port = make_server(false, requests.clone()).await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}/protected", port)).send()?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_digest_auth_sent_again() -> Result<(), anyhow::Error> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let port = make_server(false, requests.clone()).await?;
+
+    let mut req = attohttpc::get(format!("http://localhost:{}/protected", port))
+        .digest_auth("user", "pass")
+        .prepare();
+    assert!(req.send()?.text()?.contains("nc=00000001"));
+    assert!(!req.headers().contains_key("authorization"));
+
+    // Every call answers a new challenge instead of reusing the previous answer.
+    assert!(req.send()?.text()?.contains("nc=00000002"));
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_digest_auth_body_reader() -> Result<(), anyhow::Error> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let port = make_server(false, requests.clone()).await?;
+
+    let resp = attohttpc::post(format!("http://localhost:{}/protected", port))
+        .digest_auth("user", "pass")
+        .body_reader(&b"hello"[..], Some(5))
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod tools;
+
+/// Start a server which handles a single request, `handler` receives the connection once the head was read.
+fn start_server<F>(mut handler: F) -> (u16, thread::JoinHandle<Vec<()>>)
+where
+    F: FnMut(BufReader<TcpStream>) + Send + 'static,
+{
+    tools::start_raw_server(1, move |mut reader| {
+        let head = tools::read_head(&mut reader);
+        assert!(head
+            .iter()
+            .any(|line| line.eq_ignore_ascii_case("expect: 100-continue")));
+        handler(reader);
+    })
+}
+
+fn post(port: u16) -> attohttpc::Result<attohttpc::Response> {
+    attohttpc::post(format!("http://localhost:{}", port))
+        .header("Expect", "100-continue")
+        .text("hello")
+        .send()
+}
+
+#[test]
+fn test_expect_continue_accepted() {
+    let (port, thread) = start_server(|mut reader| {
+        reader.get_mut().write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+
+        let mut body = [0; 5];
+        reader.read_exact(&mut body).unwrap();
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\n{}",
+            std::str::from_utf8(&body).unwrap()
+        )
+        .unwrap();
+    });
+
+    let resp = post(port).unwrap();
+    assert_eq!(resp.status(), attohttpc::StatusCode::OK);
+    assert_eq!(resp.text().unwrap(), "hello");
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_expect_continue_rejected() {
+    let (port, thread) = start_server(|mut reader| {
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 413 Payload Too Large\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        // The body must not be sent.
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+    });
+
+    let resp = post(port).unwrap();
+    assert_eq!(resp.status(), attohttpc::StatusCode::PAYLOAD_TOO_LARGE);
+    drop(resp);
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_expect_continue_no_interim_response() {
+    let (port, thread) = start_server(|mut reader| {
+        let mut body = [0; 5];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+            .unwrap();
+    });
+
+    let start = Instant::now();
+    let resp = post(port).unwrap();
+    assert_eq!(resp.text().unwrap(), "ok");
+    assert!(start.elapsed() >= Duration::from_millis(900));
+    thread.join().unwrap();
+}
@@ -0,0 +1,24 @@
+use std::io::Write;
+
+use attohttpc::ErrorKind;
+
+mod tools;
+
+#[test]
+fn test_connection_closed_before_end_of_body() {
+    let (port, thread) = tools::start_raw_server(1, |mut reader| {
+        tools::read_head(&mut reader);
+
+        // Announce 10 bytes but close the connection after 5.
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello")
+            .unwrap();
+    });
+
+    let resp = attohttpc::get(format!("http://localhost:{}", port)).send().unwrap();
+    thread.join().unwrap();
+
+    let err = resp.bytes().unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::IncompleteBody));
+}
@@ -0,0 +1,258 @@
+use std::convert::Infallible;
+use std::io::{self, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use hyper::server::conn::AddrIncoming;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+
+mod tools;
+
+async fn make_server(connections: Arc<AtomicUsize>) -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+    async fn handler(_: Request<Body>) -> Result<Response<Body>, hyper::Error> {
+        Ok(Response::new(Body::from("hello")))
+    }
+
+    let bound = AddrIncoming::bind(&addr)?;
+    let port = bound.local_addr().port();
+
+    let make_service = make_service_fn(move |_| {
+        connections.fetch_add(1, Ordering::SeqCst);
+        async move { Ok::<_, Infallible>(service_fn(handler)) }
+    });
+    tokio::spawn(Server::builder(bound).serve(make_service));
+
+    Ok(port)
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_session_reuses_connection() -> Result<(), anyhow::Error> {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let port = make_server(connections.clone()).await?;
+
+    let sess = attohttpc::Session::new();
+    for _ in 0..3 {
+        let resp = sess.get(format!("http://localhost:{}", port)).send()?;
+        assert_eq!(resp.headers()["Content-Length"], "5");
+        assert_eq!(resp.text()?, "hello");
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_session_unread_body_closes_connection() -> Result<(), anyhow::Error> {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let port = make_server(connections.clone()).await?;
+
+    let sess = attohttpc::Session::new();
+    drop(sess.get(format!("http://localhost:{}", port)).send()?);
+    assert_eq!(sess.get(format!("http://localhost:{}", port)).send()?.text()?, "hello");
+
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_session_pool_disabled() -> Result<(), anyhow::Error> {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let port = make_server(connections.clone()).await?;
+
+    let mut sess = attohttpc::Session::new();
+    sess.pool_connections(false);
+    for _ in 0..2 {
+        assert_eq!(sess.get(format!("http://localhost:{}", port)).send()?.text()?, "hello");
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_request_without_session_closes_connection() -> Result<(), anyhow::Error> {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let port = make_server(connections.clone()).await?;
+
+    for _ in 0..2 {
+        assert_eq!(
+            attohttpc::get(format!("http://localhost:{}", port)).send()?.text()?,
+            "hello"
+        );
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+/// Read the head of a request and answer it, its body is left unread.
+fn answer(reader: &mut BufReader<TcpStream>) {
+    tools::read_head(reader);
+    reader
+        .get_mut()
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello")
+        .unwrap();
+}
+
+#[test]
+fn test_session_skips_connection_closed_while_idle() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (closed_tx, closed_rx) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        let mut reader = BufReader::new(listener.accept().unwrap().0);
+        answer(&mut reader);
+        drop(reader);
+        closed_tx.send(()).unwrap();
+
+        answer(&mut BufReader::new(listener.accept().unwrap().0));
+    });
+
+    let sess = attohttpc::Session::new();
+    assert_eq!(
+        sess.get(format!("http://localhost:{}", port))
+            .send()
+            .unwrap()
+            .text()
+            .unwrap(),
+        "hello"
+    );
+    closed_rx.recv().unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(
+        sess.get(format!("http://localhost:{}", port))
+            .send()
+            .unwrap()
+            .text()
+            .unwrap(),
+        "hello"
+    );
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_session_resends_request_when_pooled_connection_closes() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let thread = thread::spawn(move || {
+        // The connection is closed just after the second request was sent, without answering it.
+        let mut reader = BufReader::new(listener.accept().unwrap().0);
+        answer(&mut reader);
+        tools::read_head(&mut reader);
+        drop(reader);
+
+        answer(&mut BufReader::new(listener.accept().unwrap().0));
+    });
+
+    let sess = attohttpc::Session::new();
+    for _ in 0..2 {
+        assert_eq!(
+            sess.get(format!("http://localhost:{}", port))
+                .send()
+                .unwrap()
+                .text()
+                .unwrap(),
+            "hello"
+        );
+    }
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_session_does_not_resend_streamed_body() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = listener.try_clone().unwrap();
+    let thread = thread::spawn(move || {
+        let mut reader = BufReader::new(server.accept().unwrap().0);
+        answer(&mut reader);
+        tools::read_head(&mut reader);
+    });
+
+    let sess = attohttpc::Session::new();
+    assert_eq!(
+        sess.get(format!("http://localhost:{}", port))
+            .send()
+            .unwrap()
+            .text()
+            .unwrap(),
+        "hello"
+    );
+    let body = attohttpc::body::Reader::new(&b"hello"[..], Some(5));
+    assert!(sess
+        .post(format!("http://localhost:{}", port))
+        .body(body)
+        .send()
+        .is_err());
+    thread.join().unwrap();
+
+    listener.set_nonblocking(true).unwrap();
+    assert_eq!(listener.accept().unwrap_err().kind(), io::ErrorKind::WouldBlock);
+}
+
+#[test]
+fn test_session_pooled_connection_uses_request_timeouts() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = listener.try_clone().unwrap();
+    let thread = thread::spawn(move || {
+        let mut reader = BufReader::new(server.accept().unwrap().0);
+        answer(&mut reader);
+        // Slower than the timeout of the first request, within the timeout of the second one.
+        tools::read_head(&mut reader);
+        thread::sleep(Duration::from_millis(300));
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello")
+            .unwrap();
+        // Slower than the timeout of the third request.
+        tools::read_head(&mut reader);
+        thread::sleep(Duration::from_millis(500));
+    });
+
+    let sess = attohttpc::Session::new();
+    let url = format!("http://localhost:{}", port);
+    let resp = sess.get(&url).read_timeout(Duration::from_millis(100)).send().unwrap();
+    assert_eq!(resp.text().unwrap(), "hello");
+
+    let resp = sess.get(&url).read_timeout(Duration::from_secs(5)).send().unwrap();
+    assert_eq!(resp.text().unwrap(), "hello");
+
+    let err = sess
+        .get(&url)
+        .read_timeout(Duration::from_millis(100))
+        .send()
+        .unwrap_err();
+    assert!(matches!(err.kind(), attohttpc::ErrorKind::Timeout));
+    thread.join().unwrap();
+
+    // All the requests were sent on the same connection.
+    listener.set_nonblocking(true).unwrap();
+    assert_eq!(listener.accept().unwrap_err().kind(), io::ErrorKind::WouldBlock);
+}
+
+#[cfg(any(feature = "tls", feature = "tls-rustls"))]
+#[tokio::test(threaded_scheduler)]
+async fn test_session_connection_not_reused_with_other_tls_settings() -> Result<(), anyhow::Error> {
+    let port = tools::start_hello_world_server(true).await?;
+    let url = format!("https://localhost:{}", port);
+
+    let sess = attohttpc::Session::new();
+    let resp = sess.get(&url).danger_accept_invalid_certs(true).send()?;
+    assert_eq!(resp.text()?, "hello");
+
+    // The connection which accepted the invalid certificate must not be used by a strict request.
+    assert!(sess.get(&url).send().is_err());
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::io::Write;
+use std::thread;
+
+use attohttpc::{ErrorKind, Pipeline};
+
+mod tools;
+
+/// Start a server which reads `count` requests before answering all of them at once with `responses`.
+///
+/// The thread of the server returns the request line of each request.
+fn start_server(count: usize, responses: &'static [u8]) -> (u16, thread::JoinHandle<Vec<Vec<String>>>) {
+    tools::start_raw_server(1, move |mut reader| {
+        let lines = (0..count).map(|_| tools::read_head(&mut reader).remove(0)).collect();
+        reader.get_mut().write_all(responses).unwrap();
+        lines
+    })
+}
+
+#[test]
+fn test_pipeline_responses_in_order() {
+    let (port, thread) = start_server(
+        3,
+        b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none\
+          HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\ntwo\r\n0\r\n\r\n\
+          HTTP/1.1 404 Not Found\r\nContent-Length: 5\r\n\r\nthree",
+    );
+
+    let mut pipeline = Pipeline::new();
+    for path in &["a", "b", "c"] {
+        pipeline
+            .send(attohttpc::get(format!("http://localhost:{}/{}", port, path)).prepare())
+            .unwrap();
+    }
+
+    assert_eq!(pipeline.next_response().unwrap().unwrap().text().unwrap(), "one");
+    assert_eq!(pipeline.next_response().unwrap().unwrap().text().unwrap(), "two");
+    let resp = pipeline.next_response().unwrap().unwrap();
+    assert_eq!(resp.status(), attohttpc::StatusCode::NOT_FOUND);
+    assert_eq!(resp.text().unwrap(), "three");
+    assert!(pipeline.next_response().is_none());
+
+    assert_eq!(
+        thread.join().unwrap(),
+        [["GET /a HTTP/1.1", "GET /b HTTP/1.1", "GET /c HTTP/1.1"]]
+    );
+}
+
+#[test]
+fn test_pipeline_unread_body() {
+    let (port, thread) = start_server(
+        2,
+        b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none\
+          HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntwo",
+    );
+
+    let mut pipeline = Pipeline::new();
+    for _ in 0..2 {
+        pipeline
+            .send(attohttpc::get(format!("http://localhost:{}", port)).prepare())
+            .unwrap();
+    }
+
+    let first = pipeline.next_response().unwrap().unwrap();
+    let err = pipeline.next_response().unwrap().unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::PipelineBroken));
+    drop(first);
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_pipeline_unread_empty_body() {
+    let (port, thread) = start_server(
+        3,
+        b"HTTP/1.1 204 No Content\r\n\r\n\
+          HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n\
+          HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntwo",
+    );
+
+    let mut pipeline = Pipeline::new();
+    for _ in 0..3 {
+        pipeline
+            .send(attohttpc::get(format!("http://localhost:{}", port)).prepare())
+            .unwrap();
+    }
+
+    // Empty bodies hand the connection to the next response without being read.
+    drop(pipeline.next_response().unwrap().unwrap());
+    drop(pipeline.next_response().unwrap().unwrap());
+    assert_eq!(pipeline.next_response().unwrap().unwrap().text().unwrap(), "two");
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_pipeline_connection_close() {
+    let (port, thread) = start_server(
+        2,
+        b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\none",
+    );
+
+    let mut pipeline = Pipeline::new();
+    for _ in 0..2 {
+        pipeline
+            .send(attohttpc::get(format!("http://localhost:{}", port)).prepare())
+            .unwrap();
+    }
+
+    assert_eq!(pipeline.next_response().unwrap().unwrap().text().unwrap(), "one");
+    let err = pipeline.next_response().unwrap().unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::PipelineBroken));
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_pipeline_same_origin() {
+    let (port, thread) = start_server(1, b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none");
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .send(attohttpc::get(format!("http://localhost:{}", port)).prepare())
+        .unwrap();
+    let err = pipeline
+        .send(attohttpc::get(format!("http://127.0.0.1:{}", port)).prepare())
+        .unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::PipelineOrigin));
+
+    assert_eq!(pipeline.next_response().unwrap().unwrap().text().unwrap(), "one");
+    thread.join().unwrap();
+}
@@ -0,0 +1,50 @@
+#![cfg(any(feature = "tls", feature = "tls-rustls"))]
+
+mod tools;
+
+use attohttpc::ErrorKind;
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirect_downgrade_refused() -> Result<(), anyhow::Error> {
+    let http_port = tools::start_hello_world_server(false).await?;
+    let target = format!("http://localhost:{}/", http_port);
+    let https_port = tools::start_redirect_server(true, target.clone()).await?;
+
+    let err = attohttpc::get(format!("https://localhost:{}", https_port))
+        .danger_accept_invalid_certs(true)
+        .send()
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::InsecureRedirect(url) => assert_eq!(url.as_str(), target),
+        kind => panic!("unexpected error {:?}", kind),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirect_downgrade_allowed() -> Result<(), anyhow::Error> {
+    let http_port = tools::start_hello_world_server(false).await?;
+    let https_port = tools::start_redirect_server(true, format!("http://localhost:{}/", http_port)).await?;
+
+    let resp = attohttpc::get(format!("https://localhost:{}", https_port))
+        .danger_accept_invalid_certs(true)
+        .allow_downgrade(true)
+        .send()?;
+    assert_eq!(resp.text()?, "hello");
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirect_upgrade() -> Result<(), anyhow::Error> {
+    let https_port = tools::start_hello_world_server(true).await?;
+    let http_port = tools::start_redirect_server(false, format!("https://localhost:{}/", https_port)).await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}", http_port))
+        .danger_accept_invalid_certs(true)
+        .send()?;
+    assert_eq!(resp.text()?, "hello");
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 
-use attohttpc::ErrorKind;
+use attohttpc::{ErrorKind, RedirectAction};
 use warp::Filter;
 
 async fn make_server() -> Result<u16, anyhow::Error> {
@@ -9,14 +10,54 @@ async fn make_server() -> Result<u16, anyhow::Error> {
     let local_addr = incoming.local_addr()?;
 
     let a = warp::path("301").map(|| warp::redirect::redirect(http::Uri::from_static("/301")));
-    let b = warp::path("304").map(|| {
+    let b = warp::path("302").map(|| {
+        http::Response::builder()
+            .header("Location", "/302")
+            .status(http::StatusCode::FOUND)
+            .body("")
+    });
+    let c = warp::path("304").map(|| {
         http::Response::builder()
             .header("Location", "/304")
             .status(http::StatusCode::NOT_MODIFIED)
             .body("")
     });
 
-    let server = warp::serve(a.or(b)).serve_incoming(incoming);
+    let d = warp::path("303").map(|| {
+        http::Response::builder()
+            .header("Location", "/method")
+            .status(http::StatusCode::SEE_OTHER)
+            .body("")
+    });
+    let f = warp::path("307").map(|| {
+        http::Response::builder()
+            .header("Location", "/method")
+            .status(http::StatusCode::TEMPORARY_REDIRECT)
+            .body("")
+    });
+    let g = warp::path("308").map(|| {
+        http::Response::builder()
+            .header("Location", "/method")
+            .status(http::StatusCode::PERMANENT_REDIRECT)
+            .body("")
+    });
+    let e = warp::path("method").and(warp::method()).and(warp::body::bytes()).map(
+        |method: http::Method, body: warp::hyper::body::Bytes| format!("{} {}", method, String::from_utf8_lossy(&body)),
+    );
+
+    // Goes to the same server through another host name, which is a different origin.
+    let port = local_addr.port();
+    let h = warp::path("cross-origin").map(move || {
+        http::Response::builder()
+            .header("Location", format!("http://127.0.0.1:{}/authorization", port))
+            .status(http::StatusCode::FOUND)
+            .body("")
+    });
+    let i = warp::path("authorization")
+        .and(warp::header::optional::<String>("authorization"))
+        .map(|authorization: Option<String>| authorization.unwrap_or_default());
+
+    let server = warp::serve(a.or(b).or(c).or(d).or(e).or(f).or(g).or(h).or(i)).serve_incoming(incoming);
     tokio::spawn(server);
 
     Ok(local_addr.port())
@@ -37,6 +78,24 @@ async fn test_redirection_default() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_found_loop() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    match attohttpc::get(format!("http://localhost:{}/302", port))
+        .max_redirections(3)
+        .send()
+    {
+        Err(err) => match err.kind() {
+            ErrorKind::TooManyRedirections => (),
+            _ => panic!(),
+        },
+        _ => panic!(),
+    }
+
+    Ok(())
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn test_redirection_0() -> Result<(), anyhow::Error> {
     let port = make_server().await?;
@@ -80,3 +139,165 @@ async fn test_redirection_not_redirect() -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_see_other_changes_method() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let resp = attohttpc::post(format!("http://localhost:{}/303", port))
+        .text("hello")
+        .send()?;
+
+    assert_eq!(resp.url().as_str(), format!("http://localhost:{}/method", port));
+    assert_eq!(resp.text()?, "GET ");
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_temporary_keeps_method_and_body() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let resp = attohttpc::post(format!("http://localhost:{}/307", port))
+        .text("hello")
+        .send()?;
+
+    assert_eq!(resp.url().as_str(), format!("http://localhost:{}/method", port));
+    assert_eq!(resp.text()?, "POST hello");
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_permanent_keeps_method_and_body() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let resp = attohttpc::post(format!("http://localhost:{}/308", port))
+        .text("hello")
+        .send()?;
+
+    assert_eq!(resp.url().as_str(), format!("http://localhost:{}/method", port));
+    assert_eq!(resp.text()?, "POST hello");
+
+    let resp = attohttpc::put(format!("http://localhost:{}/308", port))
+        .bytes(&b"world"[..])
+        .send()?;
+    assert_eq!(resp.text()?, "PUT world");
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_policy() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let policy_calls = calls.clone();
+    let resp = attohttpc::post(format!("http://localhost:{}/303", port))
+        .redirect_policy(move |url, status| {
+            policy_calls.lock().unwrap().push((url.path().to_owned(), status));
+            RedirectAction::Follow
+        })
+        .send()?;
+
+    assert_eq!(resp.text()?, "GET ");
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![("/method".to_owned(), http::StatusCode::SEE_OTHER)]
+    );
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_policy_stop() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}/301", port))
+        .redirect_policy(|_, _| RedirectAction::Stop)
+        .send()?;
+
+    assert_eq!(resp.status(), http::StatusCode::MOVED_PERMANENTLY);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_policy_error() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let err = attohttpc::get(format!("http://localhost:{}/302", port))
+        .redirect_policy(|_, _| RedirectAction::Error)
+        .send()
+        .unwrap_err();
+
+    match err.kind() {
+        ErrorKind::RedirectRefused(url) => assert_eq!(url.path(), "/302"),
+        err => panic!("unexpected error {:?}", err),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_follow_redirects_replaces_policy() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let resp = attohttpc::post(format!("http://localhost:{}/303", port))
+        .redirect_policy(|_, _| RedirectAction::Error)
+        .follow_redirects(true)
+        .send()?;
+
+    assert_eq!(resp.text()?, "GET ");
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_disallowed_url() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}/303", port))
+        .follow_redirects(false)
+        .send()?;
+
+    assert_eq!(resp.url().as_str(), format!("http://localhost:{}/303", port));
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_history() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let url = format!("http://localhost:{}/303", port);
+    let resp = attohttpc::get(&url).record_history(true).send()?;
+    let history: Vec<_> = resp
+        .history()
+        .iter()
+        .map(|(status, url)| (*status, url.as_str().to_owned()))
+        .collect();
+    assert_eq!(history, vec![(http::StatusCode::SEE_OTHER, url)]);
+
+    let resp = attohttpc::get(format!("http://localhost:{}/303", port)).send()?;
+    assert!(resp.history().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_redirection_cross_origin_keeps_request_credentials() -> Result<(), anyhow::Error> {
+    let port = make_server().await?;
+
+    let mut req = attohttpc::get(format!("http://localhost:{}/cross-origin", port))
+        .bearer_auth("secret")
+        .prepare();
+    assert_eq!(req.send()?.text()?, "");
+    assert_eq!(req.headers()[http::header::AUTHORIZATION], "Bearer secret");
+
+    // The credentials are only removed for the redirection, sending the request again still uses them.
+    assert_eq!(req.send()?.text()?, "");
+    assert_eq!(req.headers()[http::header::AUTHORIZATION], "Bearer secret");
+
+    Ok(())
+}
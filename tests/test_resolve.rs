@@ -0,0 +1,64 @@
+use std::net::SocketAddr;
+
+async fn make_server() -> Result<SocketAddr, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let incoming = tokio::net::TcpListener::bind(&addr).await?;
+    let local_addr = incoming.local_addr()?;
+
+    let host = warp::header::<String>("host");
+    tokio::spawn(warp::serve(host).serve_incoming(incoming));
+
+    Ok(local_addr)
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_resolve_override() -> Result<(), anyhow::Error> {
+    let addr = make_server().await?;
+
+    let resp = attohttpc::get("http://example.invalid:1234/")
+        .resolve("Example.invalid", addr)
+        .send()?;
+    assert_eq!(resp.text()?, "example.invalid:1234");
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_resolve_override_session() -> Result<(), anyhow::Error> {
+    let addr = make_server().await?;
+
+    let mut sess = attohttpc::Session::new();
+    sess.resolve("example.invalid", addr);
+    let resp = sess.get("http://example.invalid/").send()?;
+    assert_eq!(resp.text()?, "example.invalid");
+
+    Ok(())
+}
+
+#[cfg(any(feature = "tls", feature = "tls-rustls"))]
+#[test]
+fn test_sni_hostname() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let thread = thread::spawn(move || {
+        // The client hello is sent in the first packet, the handshake is not completed.
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = vec![0; 16 * 1024];
+        let n = stream.read(&mut buf).unwrap();
+        buf.truncate(n);
+        buf
+    });
+
+    let res = attohttpc::get(format!("https://127.0.0.1:{}/", port))
+        .sni_hostname("staging.example.com")
+        .send();
+    assert!(res.is_err());
+
+    let client_hello = thread.join().unwrap();
+    let name = b"staging.example.com";
+    assert!(client_hello.windows(name.len()).any(|w| w == name));
+}
@@ -0,0 +1,120 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use attohttpc::{RetryPolicy, StatusCode};
+use hyper::server::conn::AddrIncoming;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Response, Server};
+
+/// Start a server which answers `503 Service Unavailable` to the given number of requests before
+/// answering `200 OK`.
+async fn make_server(failures: usize, requests: Arc<AtomicUsize>) -> Result<u16, anyhow::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let bound = AddrIncoming::bind(&addr)?;
+    let port = bound.local_addr().port();
+
+    let make_service = make_service_fn(move |_| {
+        let requests = requests.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_| {
+                let count = requests.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let resp = if count < failures {
+                        Response::builder()
+                            .status(503)
+                            .header("Retry-After", "0")
+                            .body(Body::empty())
+                    } else {
+                        Response::builder().body(Body::from("hello"))
+                    };
+                    Ok::<_, http::Error>(resp.unwrap())
+                }
+            }))
+        }
+    });
+    tokio::spawn(Server::builder(bound).serve(make_service));
+
+    Ok(port)
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_retry_status() -> Result<(), anyhow::Error> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let port = make_server(2, requests.clone()).await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}", port))
+        .retry_policy(RetryPolicy::new(3).retry_status(StatusCode::SERVICE_UNAVAILABLE))
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.text()?, "hello");
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_retry_status_exhausted() -> Result<(), anyhow::Error> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let port = make_server(5, requests.clone()).await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}", port))
+        .retry_policy(RetryPolicy::new(2).retry_status(StatusCode::SERVICE_UNAVAILABLE))
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_no_retry_body_reader() -> Result<(), anyhow::Error> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let port = make_server(1, requests.clone()).await?;
+
+    let resp = attohttpc::post(format!("http://localhost:{}", port))
+        .body_reader(&b"hello"[..], Some(5))
+        .retry_policy(RetryPolicy::new(3).retry_status(StatusCode::SERVICE_UNAVAILABLE))
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_no_retry_by_default() -> Result<(), anyhow::Error> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let port = make_server(1, requests.clone()).await?;
+
+    let resp = attohttpc::get(format!("http://localhost:{}", port)).send()?;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_retry_connection_refused() {
+    // Find a port nobody listens on.
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let policy = RetryPolicy::new(2).backoff(Duration::from_millis(50), Duration::from_millis(50));
+    let start = std::time::Instant::now();
+    let err = attohttpc::get(format!("http://127.0.0.1:{}", port))
+        .retry_policy(policy)
+        .send()
+        .unwrap_err();
+
+    match err.kind() {
+        attohttpc::ErrorKind::Io(err) => assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused),
+        err => panic!("unexpected error {:?}", err),
+    }
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
@@ -0,0 +1,100 @@
+use std::io::{self, Cursor, Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// An in-memory transport which answers with the given bytes and records what was written to it.
+struct Pipe {
+    response: Cursor<Vec<u8>>,
+    request: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Pipe {
+    fn new(response: &[u8]) -> (Pipe, Arc<Mutex<Vec<u8>>>) {
+        let request = Arc::new(Mutex::new(Vec::new()));
+        let pipe = Pipe {
+            response: Cursor::new(response.to_vec()),
+            request: request.clone(),
+        };
+        (pipe, request)
+    }
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.response.read(buf)
+    }
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.request.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_send_on_custom_stream() {
+    let (pipe, request) = Pipe::new(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+
+    let resp = attohttpc::post("http://example.com/foo")
+        .text("ping")
+        .send_on(pipe)
+        .unwrap();
+    assert_eq!(resp.status(), attohttpc::StatusCode::OK);
+    assert_eq!(resp.connection_info().remote_addr(), None);
+    assert_eq!(resp.text().unwrap(), "hello");
+
+    let request = String::from_utf8(request.lock().unwrap().clone()).unwrap();
+    assert!(request.starts_with("POST /foo HTTP/1.1\r\n"));
+    assert!(request.contains("\r\nhost: example.com\r\n"));
+    assert!(request.ends_with("\r\n\r\nping"));
+}
+
+/// A transport which fails if the response is read before the request body `ping` was written.
+struct EagerPipe(Pipe);
+
+impl Read for EagerPipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.0.request.lock().unwrap().ends_with(b"ping") {
+            return Err(io::Error::other("read before the body was sent"));
+        }
+        self.0.read(buf)
+    }
+}
+
+impl Write for EagerPipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[test]
+fn test_send_on_expect_continue_sends_body() {
+    let (pipe, request) = Pipe::new(b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+
+    let resp = attohttpc::post("http://example.com/foo")
+        .header("Expect", "100-continue")
+        .text("ping")
+        .send_on(EagerPipe(pipe))
+        .unwrap();
+    assert_eq!(resp.text().unwrap(), "hello");
+
+    let request = String::from_utf8(request.lock().unwrap().clone()).unwrap();
+    assert!(request.contains("\r\nexpect: 100-continue\r\n"));
+}
+
+#[test]
+fn test_send_on_does_not_follow_redirections() {
+    let (pipe, _) = Pipe::new(b"HTTP/1.1 302 Found\r\nLocation: /bar\r\nContent-Length: 0\r\n\r\n");
+
+    let resp = attohttpc::get("http://example.com/foo").send_on(pipe).unwrap();
+    assert_eq!(resp.status(), attohttpc::StatusCode::FOUND);
+    assert_eq!(resp.location(), Some("/bar"));
+}
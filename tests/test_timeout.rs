@@ -1,7 +1,10 @@
-use std::io;
-use std::net::TcpListener;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 
 #[test]
 fn request_fails_due_to_read_timeout() {
@@ -18,10 +21,7 @@ fn request_fails_due_to_read_timeout() {
 
     match result {
         Err(err) => match err.kind() {
-            attohttpc::ErrorKind::Io(err) => match err.kind() {
-                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => (),
-                err => panic!("Unexpected I/O error: {:?}", err),
-            },
+            attohttpc::ErrorKind::Timeout => (),
             err => panic!("Unexpected error: {:?}", err),
         },
         Ok(resp) => panic!("Unexpected response: {:?}", resp),
@@ -45,10 +45,71 @@ fn request_fails_due_to_timeout() {
 
     match result {
         Err(err) => match err.kind() {
-            attohttpc::ErrorKind::Io(err) => match err.kind() {
-                io::ErrorKind::TimedOut => (),
-                err => panic!("Unexpected I/O error: {:?}", err),
-            },
+            attohttpc::ErrorKind::Timeout => (),
+            err => panic!("Unexpected error: {:?}", err),
+        },
+        Ok(resp) => panic!("Unexpected response: {:?}", resp),
+    }
+
+    thread.join().unwrap();
+}
+
+#[test]
+fn request_fails_due_to_timeout_across_redirections() {
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf);
+            thread::sleep(Duration::from_millis(60));
+            let _ = stream.write_all(b"HTTP/1.1 302 Found\r\nLocation: /\r\nContent-Length: 0\r\n\r\n");
+        }
+    });
+
+    let result = attohttpc::get(format!("http://localhost:{}", port))
+        .timeout(Duration::from_millis(100))
+        .max_redirections(10)
+        .send();
+
+    match result {
+        Err(err) => match err.kind() {
+            attohttpc::ErrorKind::Timeout => (),
+            err => panic!("Unexpected error: {:?}", err),
+        },
+        Ok(resp) => panic!("Unexpected response: {:?}", resp),
+    }
+}
+
+#[test]
+fn request_fails_due_to_write_timeout() {
+    // Accepted connections keep the tiny receive buffer of the listener, the server never reads the body.
+    let socket = Socket::new(Domain::ipv4(), Type::stream(), Some(Protocol::tcp())).unwrap();
+    socket.set_recv_buffer_size(4096).unwrap();
+    socket
+        .bind(&SockAddr::from(SocketAddr::from(([127, 0, 0, 1], 0))))
+        .unwrap();
+    socket.listen(1).unwrap();
+    let listener = socket.into_tcp_listener();
+    let port = listener.local_addr().unwrap().port();
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+    let thread = thread::spawn(move || {
+        let _stream = listener.accept().unwrap();
+        let _ = done_rx.recv();
+    });
+
+    let start = Instant::now();
+    let result = attohttpc::post(format!("http://127.0.0.1:{}", port))
+        .write_timeout(Duration::from_millis(200))
+        .body_reader(io::repeat(0).take(1 << 30), Some(1 << 30))
+        .send();
+    assert!(start.elapsed() < Duration::from_secs(10));
+    done_tx.send(()).unwrap();
+
+    match result {
+        Err(err) => match err.kind() {
+            attohttpc::ErrorKind::Timeout => (),
             err => panic!("Unexpected error: {:?}", err),
         },
         Ok(resp) => panic!("Unexpected response: {:?}", resp),
@@ -0,0 +1,72 @@
+#![cfg(unix)]
+
+use std::io::{BufReader, Write};
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::thread;
+
+mod tools;
+
+/// Create a path for a socket which does not exist yet.
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("attohttpc-{}-{}.sock", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_unix_socket() {
+    let path = socket_path("get");
+    let listener = UnixListener::bind(&path).unwrap();
+    let thread = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let head = tools::read_head(&mut reader);
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello")
+            .unwrap();
+        head
+    });
+
+    let resp = attohttpc::get("http://docker/v1.40/info")
+        .unix_socket(&path)
+        .send()
+        .unwrap();
+    assert_eq!(resp.text().unwrap(), "hello");
+
+    let head = thread.join().unwrap();
+    assert_eq!(head[0], "GET /v1.40/info HTTP/1.1");
+    assert!(head.iter().any(|line| line.eq_ignore_ascii_case("host: docker")));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_unix_socket_https() {
+    let path = socket_path("https");
+    let _listener = UnixListener::bind(&path).unwrap();
+
+    let err = attohttpc::get("https://docker/info")
+        .unix_socket(&path)
+        .send()
+        .unwrap_err();
+    match err.kind() {
+        attohttpc::ErrorKind::InvalidBaseUrl => (),
+        err => panic!("unexpected error {:?}", err),
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_unix_socket_missing() {
+    let path = socket_path("missing");
+
+    let err = attohttpc::get("http://docker/info")
+        .unix_socket(&path)
+        .send()
+        .unwrap_err();
+    match err.kind() {
+        attohttpc::ErrorKind::Io(_) => (),
+        err => panic!("unexpected error {:?}", err),
+    }
+}
@@ -1,6 +1,11 @@
+// Every test binary only uses some of these helpers.
+#![allow(dead_code, unused_imports)]
+
 mod proxy;
+mod raw;
 mod servers;
 mod tls;
 
 pub use proxy::*;
+pub use raw::*;
 pub use servers::*;
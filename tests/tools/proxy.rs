@@ -157,3 +157,29 @@ pub async fn start_refusing_proxy_server(tls: bool) -> Result<u16, hyper::Error>
 
     Ok(addr.port())
 }
+
+pub async fn start_proxy_authorization_echo_server() -> Result<u16, hyper::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+
+    let bound = AddrIncoming::bind(&addr)?;
+    let addr = bound.local_addr();
+
+    async fn handler(req: Request<Body>) -> http::Result<Response<Body>> {
+        let auth = req
+            .headers()
+            .get("proxy-authorization")
+            .map(|val| val.as_bytes().to_vec())
+            .unwrap_or_default();
+        Response::builder()
+            .status(StatusCode::PROXY_AUTHENTICATION_REQUIRED)
+            .body(Body::from(auth))
+    }
+
+    let make_service = make_service_fn(move |_| async move { Ok::<_, Infallible>(service_fn(handler)) });
+    let server = Server::builder(bound);
+    tokio::spawn(server.serve(make_service));
+
+    println!("Listening on http://{}", addr);
+
+    Ok(addr.port())
+}
@@ -0,0 +1,35 @@
+use std::io::{BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+/// Read the head of a request and return its lines, without the empty line which ends it.
+pub fn read_head(reader: &mut impl BufRead) -> Vec<String> {
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end().to_owned();
+        if line.is_empty() {
+            return lines;
+        }
+        lines.push(line);
+    }
+}
+
+/// Start a server which accepts `connections` connections one after the other and hands each of them to `handler`.
+///
+/// The thread of the server returns what `handler` returned for each connection.
+pub fn start_raw_server<F, T>(connections: usize, mut handler: F) -> (u16, thread::JoinHandle<Vec<T>>)
+where
+    F: FnMut(BufReader<TcpStream>) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let listener = TcpListener::bind("localhost:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let thread = thread::spawn(move || {
+        (0..connections)
+            .map(|_| handler(BufReader::new(listener.accept().unwrap().0)))
+            .collect()
+    });
+    (port, thread)
+}
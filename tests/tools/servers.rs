@@ -7,6 +7,15 @@ use hyper::{Body, Request, Response, Server};
 
 use super::tls::{TlsAcceptor, TlsConfigBuilder};
 
+fn tls_acceptor(bound: AddrIncoming) -> TlsAcceptor {
+    let conf = TlsConfigBuilder::new()
+        .cert(include_bytes!("cert.pem"))
+        .key(include_bytes!("key.pem"))
+        .build()
+        .unwrap();
+    TlsAcceptor::new(conf, bound)
+}
+
 pub async fn start_hello_world_server(tls: bool) -> Result<u16, hyper::Error> {
     let addr = SocketAddr::from(([127, 0, 0, 1], 0));
 
@@ -20,13 +29,7 @@ pub async fn start_hello_world_server(tls: bool) -> Result<u16, hyper::Error> {
     if tls {
         let make_service = make_service_fn(move |_| async move { Ok::<_, Infallible>(service_fn(handler)) });
 
-        let conf = TlsConfigBuilder::new()
-            .cert(include_bytes!("cert.pem"))
-            .key(include_bytes!("key.pem"))
-            .build()
-            .unwrap();
-        let acceptor = TlsAcceptor::new(conf, bound);
-        let server = Server::builder(acceptor);
+        let server = Server::builder(tls_acceptor(bound));
         tokio::spawn(server.serve(make_service));
     } else {
         let make_service = make_service_fn(move |_| async move { Ok::<_, Infallible>(service_fn(handler)) });
@@ -39,3 +42,34 @@ pub async fn start_hello_world_server(tls: bool) -> Result<u16, hyper::Error> {
 
     Ok(addr.port())
 }
+
+/// Start a server which answers every request with a `302 Found` redirection to `location`.
+pub async fn start_redirect_server(tls: bool, location: String) -> Result<u16, hyper::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let bound = AddrIncoming::bind(&addr)?;
+    let port = bound.local_addr().port();
+
+    let handler = move |_| {
+        let resp = Response::builder()
+            .status(302)
+            .header("Location", location.as_str())
+            .body(Body::empty());
+        async move { Ok::<_, http::Error>(resp.unwrap()) }
+    };
+
+    if tls {
+        let make_service = make_service_fn(move |_| {
+            let handler = handler.clone();
+            async move { Ok::<_, Infallible>(service_fn(handler)) }
+        });
+        tokio::spawn(Server::builder(tls_acceptor(bound)).serve(make_service));
+    } else {
+        let make_service = make_service_fn(move |_| {
+            let handler = handler.clone();
+            async move { Ok::<_, Infallible>(service_fn(handler)) }
+        });
+        tokio::spawn(Server::builder(bound).serve(make_service));
+    }
+
+    Ok(port)
+}
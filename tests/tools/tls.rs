@@ -122,11 +122,13 @@ impl TlsConfigBuilder {
     }
 }
 
+#[allow(dead_code)]
 struct LazyFile {
     path: PathBuf,
     file: Option<File>,
 }
 
+#[allow(dead_code)]
 impl LazyFile {
     fn lazy_read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         if self.file.is_none() {